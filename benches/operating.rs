@@ -49,7 +49,6 @@ fn criterion_benchmark(c: &mut Criterion) {
     b.iter(|| {
       let mut data = TernaryTreeList::Empty;
 
-      // TODO overflowed
       for idx in 0..ITER_SIZE {
         let pos = idx / 2;
         data = data.insert(pos, idx, false).unwrap()
@@ -1,5 +1,5 @@
 use criterion::{criterion_group, criterion_main, Criterion};
-use im_ternary_tree::TernaryTreeList;
+use im_ternary_tree::{TernaryTreeList, TernaryTreeListBuilder};
 use std::collections::VecDeque;
 use std::hint::black_box;
 
@@ -17,6 +17,25 @@ fn push_right_benchmark(c: &mut Criterion) {
         })
     });
 
+    group.bench_function("TernaryTreeList chunked", |b| {
+        b.iter(|| {
+            let mut list = TernaryTreeList::from_chunked([0]);
+            for i in 0..ITER_SIZE {
+                list = list.push_right(black_box(i));
+            }
+        })
+    });
+
+    group.bench_function("TernaryTreeListBuilder", |b| {
+        b.iter(|| {
+            let mut builder = TernaryTreeListBuilder::new();
+            for i in 0..ITER_SIZE {
+                builder.push_right(black_box(i));
+            }
+            builder.freeze()
+        })
+    });
+
     group.bench_function("Vec", |b| {
         b.iter(|| {
             let mut list = Vec::new();
@@ -17,6 +17,15 @@ fn sequential_access_benchmark(c: &mut Criterion) {
         })
     });
 
+    let chunked = TernaryTreeList::from_chunked(0..LIST_SIZE);
+    group.bench_function("TernaryTreeList chunked", |b| {
+        b.iter(|| {
+            for i in 0..LIST_SIZE {
+                black_box(chunked.get(i));
+            }
+        })
+    });
+
     let vec = (0..LIST_SIZE).collect::<Vec<_>>();
     group.bench_function("Vec", |b| {
         b.iter(|| {
@@ -0,0 +1,104 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use im_ternary_tree::TernaryTreeList;
+use std::collections::VecDeque;
+use std::hint::black_box;
+
+const ITER_SIZE: usize = 10000;
+
+/// push 2 and drop 1 in every round, like a stack that keeps growing
+fn stack_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("stack");
+
+    group.bench_function("TernaryTreeList right", |b| {
+        b.iter(|| {
+            let mut list = TernaryTreeList::Empty;
+            for i in 0..ITER_SIZE {
+                list = list.push_right(black_box(i)).push_right(black_box(i)).drop_right();
+            }
+            list
+        })
+    });
+
+    group.bench_function("TernaryTreeList left", |b| {
+        b.iter(|| {
+            let mut list = TernaryTreeList::Empty;
+            for i in 0..ITER_SIZE {
+                list = list.push_left(black_box(i)).push_left(black_box(i)).drop_left();
+            }
+            list
+        })
+    });
+
+    group.bench_function("TernaryTreeList pop_right", |b| {
+        b.iter(|| {
+            let mut list = TernaryTreeList::Empty;
+            for i in 0..ITER_SIZE {
+                list = list.push_right(black_box(i)).push_right(black_box(i));
+                if let Some((item, rest)) = list.pop_right() {
+                    black_box(item);
+                    list = rest;
+                }
+            }
+            list
+        })
+    });
+
+    group.bench_function("Vec", |b| {
+        b.iter(|| {
+            let mut list = Vec::new();
+            for i in 0..ITER_SIZE {
+                list.push(black_box(i));
+                list.push(black_box(i));
+                list.pop();
+            }
+            list
+        })
+    });
+
+    group.bench_function("VecDeque", |b| {
+        b.iter(|| {
+            let mut list = VecDeque::new();
+            for i in 0..ITER_SIZE {
+                list.push_back(black_box(i));
+                list.push_back(black_box(i));
+                list.pop_back();
+            }
+            list
+        })
+    });
+
+    group.finish();
+}
+
+/// cost of each drop when draining lists of different sizes, drop_right and drop_left should be close
+fn drain_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("drain");
+
+    for size in [1000, 10000, 100000] {
+        let tree = (0..size).fold(TernaryTreeList::Empty, |acc, i| acc.push_right(i));
+        group.throughput(Throughput::Elements(size as u64));
+
+        group.bench_with_input(BenchmarkId::new("drop_right", size), &tree, |b, tree| {
+            b.iter(|| {
+                let mut list = tree.clone();
+                while !list.is_empty() {
+                    list = list.drop_right();
+                }
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("drop_left", size), &tree, |b, tree| {
+            b.iter(|| {
+                let mut list = tree.clone();
+                while !list.is_empty() {
+                    list = list.drop_left();
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, stack_benchmark, drain_benchmark);
+criterion_main!(benches);
@@ -1,8 +1,8 @@
 extern crate im_ternary_tree;
 
-use im_ternary_tree::TernaryTreeList;
+use im_ternary_tree::{TernaryTreeError, TernaryTreeList};
 
-pub fn main() -> Result<(), String> {
+pub fn main() -> Result<(), TernaryTreeError> {
   println!("{}", TernaryTreeList::<usize>::from(&vec![]));
 
   // assoc
@@ -0,0 +1,86 @@
+//! transient builder for editing a list in a loop.
+//! the builder owns its tree, nodes not shared with other lists are changed in place,
+//! and shared nodes are copied at first change. `freeze` turns it back into an immutable list.
+
+use crate::TernaryTreeError;
+use crate::TernaryTreeList::{self, *};
+
+/// mutable handle of a list, created by `TernaryTreeListBuilder::new()` or `TernaryTreeList::transient()`
+#[derive(Clone, Debug)]
+pub struct TernaryTreeListBuilder<T> {
+  list: TernaryTreeList<T>,
+}
+
+impl<T> Default for TernaryTreeListBuilder<T> {
+  fn default() -> Self {
+    TernaryTreeListBuilder { list: Empty }
+  }
+}
+
+impl<T> From<TernaryTreeList<T>> for TernaryTreeListBuilder<T> {
+  fn from(list: TernaryTreeList<T>) -> Self {
+    TernaryTreeListBuilder { list }
+  }
+}
+
+impl<T> TernaryTreeListBuilder<T> {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn len(&self) -> usize {
+    self.list.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.list.is_empty()
+  }
+
+  pub fn get(&self, idx: usize) -> Option<&T> {
+    self.list.get(idx)
+  }
+
+  /// turn into an immutable list, no copying
+  pub fn freeze(self) -> TernaryTreeList<T> {
+    self.list
+  }
+}
+
+impl<T: Clone> TernaryTreeListBuilder<T> {
+  /// an empty builder starts with a chunk, which is then filled in place
+  pub fn push_right(&mut self, item: T) {
+    match self.list {
+      Empty => self.list = TernaryTreeList::from_chunked([item]),
+      Tree(_) => self.list.push_right_mut(item),
+    }
+  }
+
+  pub fn push_left(&mut self, item: T) {
+    match self.list {
+      Empty => self.list = TernaryTreeList::from_chunked([item]),
+      Tree(_) => self.list.push_left_mut(item),
+    }
+  }
+
+  /// remove last element, `None` if empty
+  pub fn pop_right(&mut self) -> Option<T> {
+    self.list.pop_right_mut()
+  }
+
+  /// remove first element, `None` if empty
+  pub fn pop_left(&mut self) -> Option<T> {
+    self.list.pop_left_mut()
+  }
+
+  pub fn assoc(&mut self, idx: usize, item: T) -> Result<(), TernaryTreeError> {
+    self.list.assoc_mut(idx, item)
+  }
+}
+
+impl<T: Clone> Extend<T> for TernaryTreeListBuilder<T> {
+  fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+    for item in iter {
+      self.push_right(item);
+    }
+  }
+}
@@ -0,0 +1,39 @@
+//! list wrapper hashed by the digest cached inside branches, behind the `cached-hash` feature.
+
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+use crate::TernaryTreeList;
+
+/// list used as a key of maps, `Hash` writes `cached_hash()` instead of every element,
+/// so hashing a new version after an `assoc` only computes digests along the changed path.
+/// unlike `TernaryTreeList`, it does not hash the same as a slice of the same elements.
+/// clippy's `mutable_key_type` points at the `OnceLock`s in branches, filling them never changes the hash
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CachedHashList<T>(pub TernaryTreeList<T>);
+
+impl<T> CachedHashList<T> {
+  pub fn into_inner(self) -> TernaryTreeList<T> {
+    self.0
+  }
+}
+
+impl<T> From<TernaryTreeList<T>> for CachedHashList<T> {
+  fn from(list: TernaryTreeList<T>) -> Self {
+    CachedHashList(list)
+  }
+}
+
+impl<T> Deref for CachedHashList<T> {
+  type Target = TernaryTreeList<T>;
+
+  fn deref(&self) -> &Self::Target {
+    &self.0
+  }
+}
+
+impl<T: Hash> Hash for CachedHashList<T> {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    state.write_u64(self.0.cached_hash());
+  }
+}
@@ -0,0 +1,111 @@
+//! errors returned from list operations.
+//! messages only carry indexes and sizes, list contents are never dumped since lists might be large.
+
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TernaryTreeError {
+  /// index is not inside the list
+  IndexOutOfBounds { index: usize, len: usize },
+  /// operation requires at least one element
+  EmptyList,
+  /// range is reversed, or exceeds the list
+  InvalidRange { start: usize, end: usize },
+  /// branch size does not match sum of its children, tree is broken
+  SizeMismatch { expected: usize, actual: usize },
+  /// chunk is empty or holds more than `CHUNK_SIZE` elements
+  InvalidChunk { size: usize },
+}
+
+impl fmt::Display for TernaryTreeError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::IndexOutOfBounds { index, len } => write!(f, "index {index} is out of bound for list of size {len}"),
+      Self::EmptyList => write!(f, "list is empty"),
+      Self::InvalidRange { start, end } => write!(f, "invalid range {start}..{end}"),
+      Self::SizeMismatch { expected, actual } => {
+        write!(f, "branch size {expected} does not match sum from its children {actual}")
+      }
+      Self::InvalidChunk { size } => write!(f, "chunk of size {size} is not allowed"),
+    }
+  }
+}
+
+impl Error for TernaryTreeError {}
+
+/// errors from `TernaryTreeList::parse_inline`, offsets are in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParseInlineError {
+  /// text ended before all brackets are closed
+  UnexpectedEnd,
+  /// bracket or extra text that does not fit in the tree
+  UnexpectedChar { offset: usize },
+  /// value failed to parse with `FromStr`
+  InvalidValue { offset: usize },
+  /// branch does not have 2 or 3 children
+  InvalidBranch { offset: usize, children: usize },
+  /// chunk is empty or holds more than `CHUNK_SIZE` elements
+  InvalidChunk { offset: usize, size: usize },
+  /// branches are nested too deep to be handled safely
+  TooDeep { offset: usize },
+}
+
+impl fmt::Display for ParseInlineError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::UnexpectedEnd => write!(f, "unexpected end of text"),
+      Self::UnexpectedChar { offset } => write!(f, "unexpected character at {offset}"),
+      Self::InvalidValue { offset } => write!(f, "invalid value at {offset}"),
+      Self::InvalidBranch { offset, children } => write!(f, "branch at {offset} has {children} children, expected 2 or 3"),
+      Self::InvalidChunk { offset, size } => write!(f, "chunk at {offset} of size {size} is not allowed"),
+      Self::TooDeep { offset } => write!(f, "branch at {offset} is nested too deep"),
+    }
+  }
+}
+
+impl Error for ParseInlineError {}
+
+/// errors from `TernaryTreeList::read_snapshot`, offsets are in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SnapshotError {
+  /// data does not start with a snapshot header of a known version
+  InvalidHeader,
+  /// data ended in the middle of the snapshot
+  UnexpectedEnd,
+  /// number does not fit in `usize`
+  InvalidNumber { offset: usize },
+  /// node kind is not known
+  InvalidTag { offset: usize, tag: u8 },
+  /// node id does not point to a node read before
+  InvalidReference { offset: usize, id: usize },
+  /// chunk is empty or holds more than `CHUNK_SIZE` elements
+  InvalidChunk { offset: usize, size: usize },
+  /// element codec failed to decode bytes
+  InvalidValue { offset: usize },
+  /// branch size is too large for `usize`
+  SizeOverflow { offset: usize },
+  /// branches are nested too deep to be handled safely
+  TooDeep { offset: usize },
+  /// extra bytes after the last list
+  TrailingBytes { offset: usize },
+}
+
+impl fmt::Display for SnapshotError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::InvalidHeader => write!(f, "not a snapshot of a supported version"),
+      Self::UnexpectedEnd => write!(f, "unexpected end of snapshot"),
+      Self::InvalidNumber { offset } => write!(f, "invalid number at {offset}"),
+      Self::InvalidTag { offset, tag } => write!(f, "unknown node tag {tag} at {offset}"),
+      Self::InvalidReference { offset, id } => write!(f, "node {id} referred at {offset} is not defined before"),
+      Self::InvalidChunk { offset, size } => write!(f, "chunk at {offset} of size {size} is not allowed"),
+      Self::InvalidValue { offset } => write!(f, "invalid element at {offset}"),
+      Self::SizeOverflow { offset } => write!(f, "branch size at {offset} overflows"),
+      Self::TooDeep { offset } => write!(f, "branch at {offset} is nested too deep"),
+      Self::TrailingBytes { offset } => write!(f, "unexpected bytes after snapshot at {offset}"),
+    }
+  }
+}
+
+impl Error for SnapshotError {}
@@ -15,6 +15,15 @@
 //! (((0 1 _) (2 3 4) (5 6 _)) ((7 8 _) (9 10 _) (11 12 _)) ((13 14 _) (15 16 17) (18 19 _)))
 //! ```
 
+mod builder;
+#[cfg(feature = "cached-hash")]
+mod cached_hash;
+mod error;
+mod report;
+#[cfg(feature = "serde")]
+mod serde_impl;
+mod snapshot;
+mod stats;
 mod tree;
 mod util;
 
@@ -22,10 +31,22 @@ use std::cmp::Ordering;
 use std::fmt;
 use std::fmt::{Debug, Display};
 use std::hash::{Hash, Hasher};
-use std::ops::Index;
+use std::iter::FusedIterator;
+use std::ops::{Index, RangeBounds};
+use std::str::FromStr;
 use std::sync::Arc;
 
+pub use builder::TernaryTreeListBuilder;
+#[cfg(feature = "cached-hash")]
+pub use cached_hash::CachedHashList;
+pub use error::{ParseInlineError, SnapshotError, TernaryTreeError};
+pub use report::{StructureIssue, StructureReport};
+pub use snapshot::SnapshotCodec;
+pub use stats::{SharingStats, TreeStats};
 use tree::TernaryTree::{self, *};
+pub use tree::{CHUNK_SIZE, FormatTreeOptions};
+use tree::{TernaryTreeIntoIterator, TernaryTreeIterator};
+use util::resolve_range;
 
 /// wraps TerarnaryTreeList with support for empty
 #[derive(Clone, Debug)]
@@ -36,10 +57,7 @@ pub enum TernaryTreeList<T> {
 
 use TernaryTreeList::*;
 
-impl<T> TernaryTreeList<T>
-where
-  T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash,
-{
+impl<T> TernaryTreeList<T> {
   pub fn is_empty(&self) -> bool {
     match self {
       Empty => true,
@@ -54,24 +72,6 @@ where
     }
   }
 
-  /// turn into a representation in triples, `_` for holes
-  pub fn format_inline(&self) -> String {
-    match self {
-      Empty => String::from("_"),
-      Tree(t) => t.format_inline(),
-    }
-  }
-
-  /// items in debug display
-  pub fn format_debug(&self) -> String {
-    let mut s = String::from("(TernaryTreeList debug");
-    for x in self.iter() {
-      s.push_str(&format!(" {x:?}"));
-    }
-    s.push(')');
-    s
-  }
-
   /// get element in list by reference
   /// PERF: recursive function is slower than iterative loop with Cell in bench(using `usize`),
   /// however, Calcit is heavy in cloning(reference though... according real practice),
@@ -103,32 +103,6 @@ where
     }
   }
 
-  /// find position of element
-  pub fn index_of(&self, item: &T) -> Option<usize> {
-    match self {
-      Empty => None,
-      Tree(t) => t.index_of(item),
-    }
-  }
-
-  /// index of element from end, return 0 if found at last
-  pub fn last_index_of(&self, item: &T) -> Option<usize> {
-    match self {
-      Empty => None,
-      Tree(t) => t.last_index_of(item),
-    }
-  }
-
-  /// recursively check structure
-  pub fn eq_shape(&self, ys: &Self) -> bool {
-    match (self, ys) {
-      (Empty, Empty) => true,
-      (Empty, _) => false,
-      (_, Empty) => false,
-      (Tree(x), Tree(y)) => x.eq_shape(y),
-    }
-  }
-
   /// unchecked get reference of element
   pub fn ref_get(&self, idx: usize) -> Option<&T> {
     match self {
@@ -160,52 +134,165 @@ where
     }
   }
 
+  pub fn check_structure(&self) -> Result<(), TernaryTreeError> {
+    match self {
+      Empty => Ok(()),
+      Tree(t) => t.check_structure(),
+    }
+  }
+
+  /// walks through the whole tree and reports depths and issues, including bad shapes that `check_structure` allows
+  pub fn structure_report(&self) -> StructureReport {
+    match self {
+      Empty => StructureReport::default(),
+      Tree(t) => t.structure_report(),
+    }
+  }
+
+  /// counts nodes and depths, and estimates memory taken by nodes
+  pub fn stats(&self) -> TreeStats {
+    match self {
+      Empty => TreeStats::default(),
+      Tree(t) => t.stats(),
+    }
+  }
+
+  /// counts nodes of several lists, nodes shared between them are detected by `Arc` pointers.
+  /// takes references since cloning a list changes `Arc` strong counts
+  pub fn sharing_stats<'a, I: IntoIterator<Item = &'a TernaryTreeList<T>>>(lists: I) -> SharingStats
+  where
+    T: 'a,
+  {
+    let mut empty_lists = 0;
+    let trees = lists.into_iter().filter_map(|list| match list {
+      Empty => {
+        empty_lists += 1;
+        None
+      }
+      Tree(t) => Some(t),
+    });
+    let mut stats = TernaryTree::sharing_stats(trees);
+    stats.lists += empty_lists;
+    stats
+  }
+
+  /// binary snapshot of several lists keeping their shapes, nodes shared between them by `Arc` are written once.
+  /// elements are written with `codec`, load with `read_snapshot`
+  pub fn write_snapshot<'a, I, C>(lists: I, codec: &C) -> Vec<u8>
+  where
+    T: 'a,
+    I: IntoIterator<Item = &'a TernaryTreeList<T>>,
+    C: SnapshotCodec<T>,
+  {
+    TernaryTree::write_snapshot(
+      lists.into_iter().map(|list| match list {
+        Empty => None,
+        Tree(t) => Some(t),
+      }),
+      codec,
+    )
+  }
+
+  pub fn map<V>(&self, f: Arc<dyn Fn(&T) -> V>) -> TernaryTreeList<V> {
+    match self {
+      Empty => TernaryTreeList::Empty,
+      Tree(t) => TernaryTreeList::Tree(t.map(f)),
+    }
+  }
+
+  /// traverse all elements in list, use referenced value
+  pub fn traverse(&self, f: &mut dyn FnMut(&T)) {
+    match self {
+      Empty => (),
+      Tree(t) => t.traverse(f),
+    }
+  }
+
+  /// traverse elements in list, use referenced value,
+  /// returns `Ok` when all elements are traversed
+  pub fn traverse_result<S>(&self, f: &mut dyn FnMut(&T) -> Result<(), S>) -> Result<(), S> {
+    match self {
+      Empty => Ok(()),
+      Tree(t) => t.traverse_result(f),
+    }
+  }
+
+  pub fn iter(&self) -> TernaryTreeListRefIntoIterator<'_, T> {
+    self.into_iter()
+  }
+
+  /// iterate over elements inside a range without slicing the list,
+  /// returns `InvalidRange` error when range is reversed or goes beyond the list
+  pub fn iter_range<R: RangeBounds<usize>>(&self, range: R) -> Result<TernaryTreeListRefIntoIterator<'_, T>, TernaryTreeError> {
+    let (start, end) = resolve_range(&range, self.len())?;
+    Ok(TernaryTreeListRefIntoIterator {
+      inner: match self {
+        Empty => None,
+        Tree(t) => Some(t.iter_range(start, end)),
+      },
+    })
+  }
+}
+
+impl<T: Clone> TernaryTreeList<T> {
   // at known index, update value
-  pub fn assoc(&self, idx: usize, item: T) -> Result<Self, String> {
+  pub fn assoc(&self, idx: usize, item: T) -> Result<Self, TernaryTreeError> {
     match self {
-      Empty => Err(String::from("empty")),
+      Empty => Err(TernaryTreeError::EmptyList),
       Tree(t) => {
         if idx > self.len() - 1 {
-          Err(format!("Index too large {} for {}", idx, self.format_inline()))
+          Err(TernaryTreeError::IndexOutOfBounds {
+            index: idx,
+            len: self.len(),
+          })
         } else {
           Ok(TernaryTreeList::Tree(t.assoc(idx, item)?))
         }
       }
     }
   }
-  pub fn dissoc(&self, idx: usize) -> Result<Self, String> {
+
+  pub fn dissoc(&self, idx: usize) -> Result<Self, TernaryTreeError> {
     match self {
-      Empty => Err(String::from("calling dissoc from empty")),
+      Empty => Err(TernaryTreeError::EmptyList),
       Tree(t) => {
         if t.len() == 1 {
           if idx == 0 {
             Ok(Empty)
           } else {
-            Err(format!("Index too large {} for {}", idx, self.format_inline()))
+            Err(TernaryTreeError::IndexOutOfBounds {
+              index: idx,
+              len: self.len(),
+            })
           }
         } else if idx < t.len() {
           Ok(TernaryTreeList::Tree(t.dissoc(idx)?))
         } else {
-          Err(format!("Index too large {} for {}", idx, self.format_inline()))
+          Err(TernaryTreeError::IndexOutOfBounds {
+            index: idx,
+            len: self.len(),
+          })
         }
       }
     }
   }
+
   /// ternary tree operation of rest
-  pub fn rest(&self) -> Result<Self, String> {
+  pub fn rest(&self) -> Result<Self, TernaryTreeError> {
     let size = self.len();
     if size == 0 {
-      Err(String::from("calling rest on empty"))
+      Err(TernaryTreeError::EmptyList)
     } else if size == 1 {
       Ok(TernaryTreeList::Empty)
     } else {
       self.dissoc(0)
     }
   }
-  pub fn butlast(&self) -> Result<Self, String> {
+
+  pub fn butlast(&self) -> Result<Self, TernaryTreeError> {
     let size = self.len();
     if size == 0 {
-      Err(String::from("calling butlast on empty"))
+      Err(TernaryTreeError::EmptyList)
     } else if size == 1 {
       Ok(TernaryTreeList::Empty)
     } else {
@@ -213,18 +300,20 @@ where
     }
   }
 
-  pub fn insert(&self, idx: usize, item: T, after: bool) -> Result<Self, String> {
+  pub fn insert(&self, idx: usize, item: T, after: bool) -> Result<Self, TernaryTreeError> {
     match self {
       Empty => {
         if idx == 0 {
           Ok(TernaryTreeList::Tree(TernaryTree::Leaf(item)))
         } else {
-          Err(String::from("inserting into empty, but index is not 0"))
+          Err(TernaryTreeError::IndexOutOfBounds { index: idx, len: 0 })
         }
       }
 
       Tree(t) => {
-        if after {
+        if idx >= t.len() {
+          Err(TernaryTreeError::IndexOutOfBounds { index: idx, len: t.len() })
+        } else if after {
           Ok(TernaryTreeList::Tree(t.insert_after(idx, item)?))
         } else {
           Ok(TernaryTreeList::Tree(t.insert_before(idx, item)?))
@@ -232,14 +321,17 @@ where
       }
     }
   }
-  pub fn assoc_before(&self, idx: usize, item: T) -> Result<Self, String> {
+
+  pub fn assoc_before(&self, idx: usize, item: T) -> Result<Self, TernaryTreeError> {
     self.insert(idx, item, false)
   }
-  pub fn assoc_after(&self, idx: usize, item: T) -> Result<Self, String> {
+
+  pub fn assoc_after(&self, idx: usize, item: T) -> Result<Self, TernaryTreeError> {
     self.insert(idx, item, true)
   }
+
   // this function mutates original tree to make it more balanced
-  pub fn force_inplace_balancing(&mut self) -> Result<(), String> {
+  pub fn force_inplace_balancing(&mut self) -> Result<(), TernaryTreeError> {
     match self {
       Empty => Ok(()),
       Tree(t) => t.force_inplace_balancing(),
@@ -249,15 +341,18 @@ where
   pub fn unshift(&self, item: T) -> Self {
     self.prepend(item)
   }
+
   pub fn prepend(&self, item: T) -> Self {
     match self {
       Empty => TernaryTreeList::Tree(TernaryTree::Leaf(item)),
       Tree(t) => TernaryTreeList::Tree(t.prepend(item)),
     }
   }
+
   pub fn push(&self, item: T) -> Self {
     self.append(item)
   }
+
   /// insert_after last element, this not optimzed for performance
   pub fn append(&self, item: T) -> Self {
     match self {
@@ -265,6 +360,7 @@ where
       Tree(t) => TernaryTreeList::Tree(t.push_right(item)),
     }
   }
+
   /// optimized for amortized `O(1)` performance at best cases
   pub fn push_right(&self, item: T) -> Self {
     match self {
@@ -272,6 +368,7 @@ where
       Tree(t) => TernaryTreeList::Tree(t.push_right(item)),
     }
   }
+
   /// optimized for amortized `O(1)` performance at best cases
   pub fn push_left(&self, item: T) -> Self {
     match self {
@@ -323,6 +420,104 @@ where
     }
   }
 
+  /// first element and the rest of the list, in a single descent. `None` if empty
+  pub fn pop_left(&self) -> Option<(T, Self)> {
+    match self {
+      Empty => None,
+      Tree(t) => {
+        if t.len() == 1 {
+          Some((t.loop_first().to_owned(), Self::Empty))
+        } else {
+          let (item, rest) = t.pop_left();
+          Some((item.to_owned(), Self::Tree(rest)))
+        }
+      }
+    }
+  }
+
+  /// last element and the rest of the list, in a single descent. `None` if empty
+  pub fn pop_right(&self) -> Option<(T, Self)> {
+    match self {
+      Empty => None,
+      Tree(t) => {
+        if t.len() == 1 {
+          Some((t.loop_last().to_owned(), Self::Empty))
+        } else {
+          let (item, rest) = t.pop_right();
+          Some((item.to_owned(), Self::Tree(rest)))
+        }
+      }
+    }
+  }
+
+  /// like `push_right`, but edits in place. branches only owned by this list are reused,
+  /// branches shared with other lists are copied, so clones of this list are not affected
+  pub fn push_right_mut(&mut self, item: T) {
+    match self {
+      Empty => *self = TernaryTreeList::Tree(TernaryTree::Leaf(item)),
+      Tree(t) => t.push_right_mut(item),
+    }
+  }
+
+  /// like `push_left`, but edits in place
+  pub fn push_left_mut(&mut self, item: T) {
+    match self {
+      Empty => *self = TernaryTreeList::Tree(TernaryTree::Leaf(item)),
+      Tree(t) => t.push_left_mut(item),
+    }
+  }
+
+  /// like `assoc`, but edits in place
+  pub fn assoc_mut(&mut self, idx: usize, item: T) -> Result<(), TernaryTreeError> {
+    match self {
+      Empty => Err(TernaryTreeError::EmptyList),
+      Tree(t) => {
+        if idx < t.len() {
+          t.assoc_mut(idx, item);
+          Ok(())
+        } else {
+          Err(TernaryTreeError::IndexOutOfBounds { index: idx, len: t.len() })
+        }
+      }
+    }
+  }
+
+  /// remove first element in place and return it, `None` if empty
+  pub fn pop_left_mut(&mut self) -> Option<T> {
+    match self {
+      Empty => None,
+      Tree(t) if t.len() > 1 => Some(t.pop_left_mut()),
+      Tree(_) => self.take_single(),
+    }
+  }
+
+  /// remove last element in place and return it, `None` if empty
+  pub fn pop_right_mut(&mut self) -> Option<T> {
+    match self {
+      Empty => None,
+      Tree(t) if t.len() > 1 => Some(t.pop_right_mut()),
+      Tree(_) => self.take_single(),
+    }
+  }
+
+  /// like `drop_left`, but edits in place
+  pub fn drop_left_mut(&mut self) {
+    self.pop_left_mut();
+  }
+
+  /// like `drop_right`, but edits in place
+  pub fn drop_right_mut(&mut self) {
+    self.pop_right_mut();
+  }
+
+  /// take the only element out, leaving list empty
+  fn take_single(&mut self) -> Option<T> {
+    match std::mem::replace(self, Empty) {
+      Empty => None,
+      Tree(t) => Some(t.into_single()),
+    }
+  }
+
   /// split into 2 lists, either could be Empty
   /// notice if index is too large, (Self, Empty) is returned, not providing index out of bound error
   pub fn split(self, idx: usize) -> (Self, Self) {
@@ -372,6 +567,16 @@ where
       TernaryTreeList::Tree(TernaryTree::concat(&trees))
     }
   }
+
+  /// append items from an iterator, packed into chunks and built into a balanced tree at once,
+  /// which is joined at the end of the right side so the tree stays shallow after many calls
+  pub fn append_iter<I: IntoIterator<Item = T>>(&self, items: I) -> Self {
+    match self {
+      Empty => TernaryTreeList::from_chunked(items),
+      Tree(t) => TernaryTreeList::Tree(t.append_iter(items)),
+    }
+  }
+
   pub fn concat_dumb(raw: &[TernaryTreeList<T>]) -> Self {
     let mut trees: Vec<TernaryTree<T>> = vec![];
     for x in raw {
@@ -386,77 +591,81 @@ where
       TernaryTreeList::Tree(TernaryTree::concat_dumb(&trees))
     }
   }
-  pub fn check_structure(&self) -> Result<(), String> {
-    match self {
-      Empty => Ok(()),
-      Tree(t) => t.check_structure(),
-    }
+
+  /// get a builder for editing in place, the list itself is not changed,
+  /// branches shared with this list are copied when they are changed in the builder
+  pub fn transient(&self) -> TernaryTreeListBuilder<T> {
+    TernaryTreeListBuilder::from(self.to_owned())
   }
-  // excludes value at end_idx, kept aligned with JS & Clojure
-  pub fn slice(&self, start_idx: usize, end_idx: usize) -> Result<Self, String> {
-    if start_idx == end_idx {
-      return Ok(TernaryTreeList::Empty);
-    }
-    match self {
-      Empty => Err(String::from("empty")),
-      Tree(t) => {
-        // echo "slice {tree.formatListInline}: {start_idx}..{end_idx}"
-        if end_idx > self.len() {
-          return Err(format!("Slice range too large {} for {}", end_idx, self.format_inline()));
-        }
-        if start_idx > end_idx {
-          return Err(format!("Invalid slice range {start_idx}..{end_idx} for {self}"));
-        }
-        if start_idx == end_idx {
-          return Ok(TernaryTreeList::Empty);
-        }
-        Ok(TernaryTreeList::Tree(t.slice(start_idx, end_idx)?))
-      }
+
+  /// build a list with elements packed into chunks of `CHUNK_SIZE`, which takes much less allocations and memory.
+  /// `push_right` and `push_left` keep filling chunks at both ends, other operations keep chunks at their places.
+  /// `collect` and `append_iter` build lists in the same way, while `From` keeps one element in each leaf
+  pub fn from_chunked<I: IntoIterator<Item = T>>(items: I) -> Self {
+    let chunks = TernaryTree::chunk_leaves(items);
+    if chunks.is_empty() {
+      TernaryTreeList::Empty
+    } else {
+      TernaryTreeList::Tree(TernaryTree::rebuild_owned(chunks))
     }
   }
 
-  pub fn skip(&self, idx: usize) -> Result<Self, String> {
-    // self.slice(idx, self.len())
-
-    match self {
-      Empty => Ok(TernaryTreeList::Empty),
-      Tree(t) => {
-        let size = t.len();
-        match idx.cmp(&size) {
-          Ordering::Equal => Ok(TernaryTreeList::Empty),
-          Ordering::Greater => Err(format!("Skip range too large {} for {}", idx, self.format_inline())),
-          Ordering::Less => Ok(TernaryTreeList::Tree(t.take_right(idx)?)),
-        }
-      }
-    }
+  /// loads lists from `write_snapshot` with the same shapes, nodes shared in the snapshot are shared by `Arc` again
+  pub fn read_snapshot<C: SnapshotCodec<T>>(bytes: &[u8], codec: &C) -> Result<Vec<Self>, SnapshotError> {
+    let trees = TernaryTree::read_snapshot(bytes, codec)?;
+    Ok(trees.into_iter().map(|tree| tree.map_or(Empty, Tree)).collect())
   }
-  pub fn take(&self, idx: usize) -> Result<Self, String> {
+
+  /// get a sub list with range bounds, it shares branches with original list.
+  /// - empty range inside the list(including `0..0` of an empty list) gives `Empty`
+  /// - `InvalidRange` is returned when start is after end, or end goes beyond the list
+  pub fn range<R: RangeBounds<usize>>(&self, range: R) -> Result<Self, TernaryTreeError> {
+    let (start, end) = resolve_range(&range, self.len())?;
     match self {
       Empty => Ok(TernaryTreeList::Empty),
       Tree(t) => {
-        if idx == 0 {
+        if start == end {
           Ok(TernaryTreeList::Empty)
-        } else if idx > self.len() {
-          Err(format!("Take range too large {} for {}", idx, self.format_inline()))
+        } else if start == 0 && end == t.len() {
+          Ok(self.to_owned())
+        } else if start == 0 {
+          Ok(TernaryTreeList::Tree(t.take_left(end)?))
+        } else if end == t.len() {
+          Ok(TernaryTreeList::Tree(t.take_right(start)?))
         } else {
-          Ok(TernaryTreeList::Tree(t.take_left(idx)?))
+          Ok(TernaryTreeList::Tree(t.slice(start, end)?))
         }
       }
     }
   }
 
+  /// like `range`, but returns `None` for ranges not inside the list
+  pub fn get_range<R: RangeBounds<usize>>(&self, range: R) -> Option<Self> {
+    self.range(range).ok()
+  }
+
+  /// excludes value at end_idx, kept aligned with JS & Clojure,
+  /// same as `range(start_idx..end_idx)`
+  pub fn slice(&self, start_idx: usize, end_idx: usize) -> Result<Self, TernaryTreeError> {
+    self.range(start_idx..end_idx)
+  }
+
+  /// drop first `idx` elements, same as `range(idx..)`
+  pub fn skip(&self, idx: usize) -> Result<Self, TernaryTreeError> {
+    self.range(idx..)
+  }
+
+  /// take first `idx` elements, same as `range(..idx)`
+  pub fn take(&self, idx: usize) -> Result<Self, TernaryTreeError> {
+    self.range(..idx)
+  }
+
   pub fn reverse(&self) -> Self {
     match self {
       Empty => TernaryTreeList::Empty,
       Tree(t) => TernaryTreeList::Tree(t.reverse()),
     }
   }
-  pub fn map<V>(&self, f: Arc<dyn Fn(&T) -> V>) -> TernaryTreeList<V> {
-    match self {
-      Empty => TernaryTreeList::Empty,
-      Tree(t) => TernaryTreeList::Tree(t.map(f)),
-    }
-  }
 
   pub fn to_vec(&self) -> Vec<T> {
     match self {
@@ -464,36 +673,139 @@ where
       Tree(t) => t.to_vec(),
     }
   }
+}
 
-  /// traverse all elements in list, use referenced value
-  pub fn traverse(&self, f: &mut dyn FnMut(&T)) {
+impl<T: Display> TernaryTreeList<T> {
+  /// turn into a representation in triples, `_` for holes
+  pub fn format_inline(&self) -> String {
     match self {
-      Empty => (),
-      Tree(t) => t.traverse(f),
+      Empty => String::from("_"),
+      Tree(t) => t.format_inline(),
     }
   }
 
-  /// traverse elements in list, use referenced value,
-  /// returns `Ok` when all elements are traversed
-  pub fn traverse_result<S>(&self, f: &mut dyn FnMut(&T) -> Result<(), S>) -> Result<(), S> {
+  /// one node in each line with kind, size and depth, children are indented below parents
+  pub fn format_tree(&self, opts: &FormatTreeOptions) -> String {
     match self {
-      Empty => Ok(()),
-      Tree(t) => t.traverse_result(f),
+      Empty => String::from("Empty\n"),
+      Tree(t) => t.format_tree(opts),
     }
   }
 
-  pub fn iter(&self) -> TernaryTreeListRefIntoIterator<T> {
-    TernaryTreeListRefIntoIterator {
-      value: self,
-      index: 0,
-      size: self.len(),
+  /// Graphviz DOT of the tree, branches are labelled with sizes and leaves with values
+  pub fn to_dot(&self) -> String {
+    Self::versions_to_dot([self])
+  }
+
+  /// Graphviz DOT of several versions of a list in one graph, nodes shared between them are drawn once and filled with gray
+  pub fn versions_to_dot<'a, I: IntoIterator<Item = &'a TernaryTreeList<T>>>(lists: I) -> String
+  where
+    T: 'a,
+  {
+    TernaryTree::versions_to_dot(lists.into_iter().map(|list| match list {
+      Empty => None,
+      Tree(t) => Some(t),
+    }))
+  }
+}
+
+impl<T: FromStr> TernaryTreeList<T> {
+  /// reverse of `format_inline`, keeps the exact shape of branches and chunks, `_` for an empty list.
+  /// values containing whitespace or brackets can not be parsed back
+  pub fn parse_inline(text: &str) -> Result<Self, ParseInlineError> {
+    if text.trim() == "_" {
+      Ok(Empty)
+    } else {
+      Ok(Tree(TernaryTree::parse_inline(text)?))
+    }
+  }
+}
+
+impl<T: Debug> TernaryTreeList<T> {
+  /// items in debug display
+  pub fn format_debug(&self) -> String {
+    let mut s = String::from("(TernaryTreeList debug");
+    for x in self.iter() {
+      s.push_str(&format!(" {x:?}"));
+    }
+    s.push(')');
+    s
+  }
+}
+
+impl<T: PartialEq> TernaryTreeList<T> {
+  /// find position of element
+  pub fn index_of(&self, item: &T) -> Option<usize> {
+    match self {
+      Empty => None,
+      Tree(t) => t.index_of(item),
+    }
+  }
+
+  /// index of element from end, return 0 if found at last
+  pub fn last_index_of(&self, item: &T) -> Option<usize> {
+    match self {
+      Empty => None,
+      Tree(t) => t.last_index_of(item),
+    }
+  }
+
+  /// recursively check structure
+  pub fn eq_shape(&self, ys: &Self) -> bool {
+    match (self, ys) {
+      (Empty, Empty) => true,
+      (Empty, _) => false,
+      (_, Empty) => false,
+      (Tree(x), Tree(y)) => x.eq_shape(y),
+    }
+  }
+}
+
+impl<T: Eq> TernaryTreeList<T> {
+  /// same result as `==`, but subtrees shared by both lists are skipped by pointer,
+  /// so comparing two versions of a large list that differ in one slot is close to `O(log n)`.
+  /// `==` can't skip them since it also accepts `T` like `f64`, where `NaN` is not equal to itself
+  pub fn eq_shared(&self, ys: &Self) -> bool {
+    match (self, ys) {
+      (Empty, Empty) => true,
+      (Tree(x), Tree(y)) => x.eq_shared(y),
+      _ => false,
+    }
+  }
+}
+
+impl<T: PartialOrd> TernaryTreeList<T> {
+  /// compare element by element like slices, a prefix comes before longer lists
+  pub fn partial_cmp_lexicographic(&self, other: &Self) -> Option<Ordering> {
+    self.iter().partial_cmp(other.iter())
+  }
+}
+
+impl<T: Ord> TernaryTreeList<T> {
+  /// compare element by element like slices, a prefix comes before longer lists,
+  /// so `[2]` is greater than `[1, 5]`, which is different from the default `Ord` that compares sizes first
+  pub fn cmp_lexicographic(&self, other: &Self) -> Ordering {
+    self.iter().cmp(other.iter())
+  }
+}
+
+#[cfg(feature = "cached-hash")]
+impl<T: Hash> TernaryTreeList<T> {
+  /// hash decided only by elements, lists in different shapes get same value when elements are equal.
+  /// unlike `Hash` which walks all leaves, digests are cached in branches and reused by versions sharing them,
+  /// so after an `assoc` only the changed path is computed again.
+  /// wrap a list in `CachedHashList` to use this hash in maps
+  pub fn cached_hash(&self) -> u64 {
+    match self {
+      Empty => tree::finish_digest(0, 0),
+      Tree(t) => t.cached_hash(),
     }
   }
 }
 
 impl<T> Display for TernaryTreeList<T>
 where
-  T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash,
+  T: Display,
 {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     match self {
@@ -503,47 +815,97 @@ where
   }
 }
 
-// experimental code to turn `&TernaryTree<_>` into iterator
-impl<'a, T> IntoIterator for &'a TernaryTreeList<T>
-where
-  T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash,
-{
+impl<'a, T> IntoIterator for &'a TernaryTreeList<T> {
   type Item = &'a T;
   type IntoIter = TernaryTreeListRefIntoIterator<'a, T>;
 
   fn into_iter(self) -> Self::IntoIter {
     TernaryTreeListRefIntoIterator {
-      value: self,
-      index: 0,
-      size: self.len(),
+      inner: match self {
+        Empty => None,
+        Tree(t) => Some(t.iter()),
+      },
     }
   }
 }
 
+/// iterates over references of elements, `None` inside for empty list
 pub struct TernaryTreeListRefIntoIterator<'a, T> {
-  value: &'a TernaryTreeList<T>,
-  index: usize,
-  size: usize,
+  inner: Option<TernaryTreeIterator<'a, T>>,
 }
 
-impl<'a, T> Iterator for TernaryTreeListRefIntoIterator<'a, T>
-where
-  T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash,
-{
+impl<'a, T> Iterator for TernaryTreeListRefIntoIterator<'a, T> {
   type Item = &'a T;
+
   fn next(&mut self) -> Option<Self::Item> {
-    if self.index < self.size {
-      // println!("get: {} {}", self.value.format_inline(), self.index);
-      let ret = self.value.loop_get(self.index);
-      self.index += 1;
-      ret
-    } else {
-      None
+    self.inner.as_mut()?.next()
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    match &self.inner {
+      Some(it) => it.size_hint(),
+      None => (0, Some(0)),
     }
   }
 }
 
-impl<T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash> PartialEq for TernaryTreeList<T> {
+impl<T> DoubleEndedIterator for TernaryTreeListRefIntoIterator<'_, T> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    self.inner.as_mut()?.next_back()
+  }
+}
+
+impl<T> ExactSizeIterator for TernaryTreeListRefIntoIterator<'_, T> {}
+
+impl<T> FusedIterator for TernaryTreeListRefIntoIterator<'_, T> {}
+
+impl<T: Clone> IntoIterator for TernaryTreeList<T> {
+  type Item = T;
+  type IntoIter = TernaryTreeListIntoIterator<T>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    TernaryTreeListIntoIterator {
+      inner: match self {
+        Empty => None,
+        Tree(t) => Some(t.into_iter()),
+      },
+    }
+  }
+}
+
+/// iterates over owned elements, values are moved out from nodes not shared with other lists,
+/// and cloned from shared nodes
+pub struct TernaryTreeListIntoIterator<T> {
+  inner: Option<TernaryTreeIntoIterator<T>>,
+}
+
+impl<T: Clone> Iterator for TernaryTreeListIntoIterator<T> {
+  type Item = T;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self.inner.as_mut()?.next()
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    match &self.inner {
+      Some(it) => it.size_hint(),
+      None => (0, Some(0)),
+    }
+  }
+}
+
+impl<T: Clone> DoubleEndedIterator for TernaryTreeListIntoIterator<T> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    self.inner.as_mut()?.next_back()
+  }
+}
+
+impl<T: Clone> ExactSizeIterator for TernaryTreeListIntoIterator<T> {}
+
+impl<T: Clone> FusedIterator for TernaryTreeListIntoIterator<T> {}
+
+/// compares every element, see `eq_shared` for skipping shared subtrees when `T: Eq`
+impl<T: PartialEq> PartialEq for TernaryTreeList<T> {
   fn eq(&self, ys: &Self) -> bool {
     match (self, ys) {
       (Empty, Empty) => true,
@@ -553,20 +915,28 @@ impl<T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash> Part
   }
 }
 
-impl<T> Eq for TernaryTreeList<T> where T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash {}
+impl<T> Eq for TernaryTreeList<T> where T: Eq {}
 
+/// `Empty` comes first, see ordering of `TernaryTree` for the rest.
+/// use `cmp_lexicographic` for ordering like slices without enabling feature `lexicographic-ord`.
+/// `partial_cmp` compares every element like `==`, while `cmp` skips subtrees shared by both lists
 impl<T> PartialOrd for TernaryTreeList<T>
 where
-  T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash,
+  T: PartialOrd,
 {
   fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-    Some(self.cmp(other))
+    match (self, other) {
+      (Empty, Empty) => Some(Ordering::Equal),
+      (Empty, _) => Some(Ordering::Less),
+      (_, Empty) => Some(Ordering::Greater),
+      (Tree(l), Tree(r)) => l.partial_cmp(r),
+    }
   }
 }
 
 impl<T> Ord for TernaryTreeList<T>
 where
-  T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash,
+  T: Ord,
 {
   fn cmp(&self, other: &Self) -> Ordering {
     match (self, other) {
@@ -578,10 +948,7 @@ where
   }
 }
 
-impl<T> Index<usize> for TernaryTreeList<T>
-where
-  T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash,
-{
+impl<T> Index<usize> for TernaryTreeList<T> {
   type Output = T;
 
   fn index<'b>(&self, idx: usize) -> &Self::Output {
@@ -596,21 +963,40 @@ where
   }
 }
 
+/// hashing is defined by contents only: the length is written first, then each element in order,
+/// the same as `[T]` and `Vec<T>` do, so lists built in different shapes hash identically.
+/// for hashers that only depend on the stream of written bytes(like `DefaultHasher`),
+/// a list also gets same hash as a slice with same elements, which is required by `Borrow<[T]>`-like lookups.
+/// that is why cached digests are not used here, they can't produce the same value as a slice,
+/// `CachedHashList` hashes with them instead, behind the `cached-hash` feature.
 impl<T> Hash for TernaryTreeList<T>
 where
-  T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash,
+  T: Hash,
 {
   fn hash<H: Hasher>(&self, state: &mut H) {
-    match self {
-      Empty => {}
-      Tree(t) => t.hash(state),
+    state.write_usize(self.len());
+    if let Tree(t) = self {
+      t.hash(state);
     }
   }
 }
 
+impl<T: Clone> FromIterator<T> for TernaryTreeList<T> {
+  fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+    TernaryTreeList::Empty.append_iter(iter)
+  }
+}
+
+impl<T: Clone> Extend<T> for TernaryTreeList<T> {
+  fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+    *self = self.append_iter(iter);
+  }
+}
+
+/// one element in each leaf, in the shapes `format_inline` has always shown, use `collect` or `from_chunked` for chunks
 impl<T> From<Vec<T>> for TernaryTreeList<T>
 where
-  T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash,
+  T: Clone,
 {
   fn from(xs: Vec<T>) -> Self {
     if xs.is_empty() {
@@ -628,7 +1014,7 @@ where
 
 impl<T> From<&Vec<T>> for TernaryTreeList<T>
 where
-  T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash,
+  T: Clone,
 {
   fn from(xs: &Vec<T>) -> Self {
     if xs.is_empty() {
@@ -647,7 +1033,7 @@ where
 // https://blog.rust-lang.org/2021/02/26/const-generics-mvp-beta.html
 impl<T, const N: usize> From<&[T; N]> for TernaryTreeList<T>
 where
-  T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash,
+  T: Clone,
 {
   fn from(xs: &[T; N]) -> Self {
     if xs.is_empty() {
@@ -0,0 +1,61 @@
+//! structured result of checking a list, for tests and fuzzers watching shapes of lists.
+
+/// problem found inside a tree. `offset` is the index of the first element inside that node,
+/// and `depth` counts branches above it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StructureIssue {
+  /// branch size does not match sum of its children, tree is broken
+  SizeMismatch {
+    offset: usize,
+    depth: usize,
+    expected: usize,
+    actual: usize,
+  },
+  /// chunk is empty or holds more than `CHUNK_SIZE` elements, tree is broken
+  InvalidChunk { offset: usize, depth: usize, size: usize },
+  /// deepest leaf is below the depth allowed for this size
+  TooDeep { depth: usize, limit: usize },
+  /// first or last leaf is below the depth allowed for this size, so pushes and pops at that end walk a long path
+  FingerTooDeep { offset: usize, depth: usize, limit: usize },
+}
+
+impl StructureIssue {
+  /// sizes or chunks are wrong, rather than the tree being in a bad shape
+  pub fn is_broken(&self) -> bool {
+    matches!(self, Self::SizeMismatch { .. } | Self::InvalidChunk { .. })
+  }
+}
+
+/// returned by `TernaryTreeList::structure_report`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StructureReport {
+  pub len: usize,
+  /// number of leaves and chunks
+  pub units: usize,
+  /// levels of branches above the deepest leaf or chunk
+  pub max_depth: usize,
+  /// depth of a balanced ternary tree with as many leaves and chunks, `ceil(log3(units))`
+  pub balanced_depth: usize,
+  /// deepest allowed for this size, `insert` and `dissoc` rebuild branches deeper than this
+  pub depth_limit: usize,
+  /// depth of the first leaf or chunk
+  pub left_edge_depth: usize,
+  /// depth of the last leaf or chunk
+  pub right_edge_depth: usize,
+  /// largest side of branches on the middle path, in percent of what `push_left` and `push_right` would put there.
+  /// pops and `concat` may leave it above 100, which is not an issue, but pushes at that end go deeper
+  pub finger_fill: usize,
+  pub issues: Vec<StructureIssue>,
+}
+
+impl StructureReport {
+  /// no issues found
+  pub fn is_ok(&self) -> bool {
+    self.issues.is_empty()
+  }
+
+  /// sizes and chunks are correct, the shape might still be bad
+  pub fn is_valid(&self) -> bool {
+    !self.issues.iter().any(StructureIssue::is_broken)
+  }
+}
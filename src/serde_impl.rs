@@ -0,0 +1,56 @@
+//! `Serialize` and `Deserialize` behind the `serde` feature.
+//! lists are written as flat sequences, the same as `Vec<T>`, so shapes of trees are not kept.
+//! deserializing builds a balanced tree like `from_chunked`, elements are moved into chunks without cloning.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+
+use crate::TernaryTreeList;
+
+impl<T: Serialize> Serialize for TernaryTreeList<T> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut seq = serializer.serialize_seq(Some(self.len()))?;
+    for item in self {
+      seq.serialize_element(item)?;
+    }
+    seq.end()
+  }
+}
+
+struct ListVisitor<T> {
+  marker: PhantomData<T>,
+}
+
+impl<'de, T: Deserialize<'de> + Clone> Visitor<'de> for ListVisitor<T> {
+  type Value = TernaryTreeList<T>;
+
+  fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    formatter.write_str("a sequence")
+  }
+
+  fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+    // elements go into chunks right away, and get balanced in one pass
+    let mut error = None;
+    let items = std::iter::from_fn(|| match seq.next_element() {
+      Ok(item) => item,
+      Err(e) => {
+        error = Some(e);
+        None
+      }
+    });
+    let list = TernaryTreeList::from_chunked(items);
+    match error {
+      Some(e) => Err(e),
+      None => Ok(list),
+    }
+  }
+}
+
+impl<'de, T: Deserialize<'de> + Clone> Deserialize<'de> for TernaryTreeList<T> {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    deserializer.deserialize_seq(ListVisitor { marker: PhantomData })
+  }
+}
@@ -0,0 +1,9 @@
+//! element codecs for binary snapshots from `TernaryTreeList::write_snapshot`
+
+/// turns elements into bytes and back. snapshots store the length of each element,
+/// so `decode` receives exactly the bytes written by `encode`
+pub trait SnapshotCodec<T> {
+  fn encode(&self, item: &T, out: &mut Vec<u8>);
+  /// `None` when bytes do not make a valid element
+  fn decode(&self, bytes: &[u8]) -> Option<T>;
+}
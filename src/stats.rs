@@ -0,0 +1,50 @@
+//! counts of nodes and memory estimates, for capacity planning.
+//! memory is estimated from sizes of nodes and chunk buffers, memory owned by elements themselves is not included.
+
+/// returned by `TernaryTreeList::stats`, nodes are counted as reached from the root
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TreeStats {
+  pub len: usize,
+  pub leaves: usize,
+  pub chunks: usize,
+  pub branch2: usize,
+  pub branch3: usize,
+  /// depth of the shallowest leaf or chunk, counting branches above it
+  pub min_depth: usize,
+  /// depth of the deepest leaf or chunk
+  pub max_depth: usize,
+  /// average depth of leaves and chunks
+  pub avg_depth: f64,
+  /// nodes whose `Arc` is also held by other lists, or by other places in this list
+  pub shared_nodes: usize,
+  /// estimated heap bytes of nodes and chunk buffers
+  pub heap_bytes: usize,
+}
+
+/// returned by `TernaryTreeList::sharing_stats`, nodes reached from several lists are detected by `Arc` pointers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SharingStats {
+  /// lists that were checked
+  pub lists: usize,
+  /// nodes counted as if nothing is shared
+  pub total_nodes: usize,
+  /// nodes counted once no matter how many times they are reached
+  pub unique_nodes: usize,
+  /// unique nodes with an `Arc` strong count greater than 1, which may also be held by lists outside this set
+  pub arc_shared_nodes: usize,
+  /// estimated heap bytes as if nothing is shared
+  pub total_heap_bytes: usize,
+  /// estimated heap bytes actually used
+  pub unique_heap_bytes: usize,
+}
+
+impl SharingStats {
+  /// part of nodes saved by sharing, `0.0` when nothing is shared
+  pub fn sharing_ratio(&self) -> f64 {
+    if self.total_nodes == 0 {
+      0.0
+    } else {
+      1.0 - self.unique_nodes as f64 / self.total_nodes as f64
+    }
+  }
+}
@@ -2,7 +2,18 @@
 //! this file should be optimized for speed, even to skip some checks repeatedly down the tree,
 //! checks can be added at the struct that wraps this tree
 
+mod check;
+mod dot;
 mod finger;
+mod format;
+#[cfg(feature = "cached-hash")]
+mod hash_cache;
+mod iter;
+mod node_cache;
+mod parse;
+mod snapshot;
+mod stats;
+mod transient;
 
 use std::cell::Cell;
 use std::cmp::Ordering;
@@ -12,34 +23,51 @@ use std::hash::{Hash, Hasher};
 use std::ops::Index;
 use std::sync::Arc;
 
-use crate::util::{divide_ternary_sizes, triple_size};
+use crate::error::TernaryTreeError;
+use crate::util::{depth_limit, divide_ternary_sizes, triple_size};
+
+pub use format::FormatTreeOptions;
+#[cfg(feature = "cached-hash")]
+pub(crate) use hash_cache::finish_digest;
+pub use iter::{TernaryTreeIntoIterator, TernaryTreeIterator};
+pub(crate) use node_cache::NodeCache;
+
+/// max number of elements inside a chunk
+pub const CHUNK_SIZE: usize = 32;
+
+/// deepest branches accepted when reading trees from text or snapshots,
+/// since walking through a tree, including dropping it, takes stack for each level
+pub(crate) const MAX_READ_DEPTH: usize = 1024;
 
 /// internal tree structure, it can't be empty
 #[derive(Clone, Debug)]
 pub enum TernaryTree<T> {
   Leaf(T),
+  /// elements stored together in a leaf, never empty, and no more than `CHUNK_SIZE` elements
+  Chunk(Vec<T>),
   Branch2 {
     size: usize,
     left: Arc<TernaryTree<T>>,
     middle: Arc<TernaryTree<T>>,
+    /// depth and digest, lazily computed, a new branch starts with an empty one
+    cache: NodeCache,
   },
   Branch3 {
     size: usize,
     left: Arc<TernaryTree<T>>,
     middle: Arc<TernaryTree<T>>,
     right: Arc<TernaryTree<T>>,
+    cache: NodeCache,
   },
 }
 
 use TernaryTree::*;
 
-impl<'a, T> TernaryTree<T>
-where
-  T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash,
-{
+impl<T> TernaryTree<T> {
   pub fn len(&self) -> usize {
     match self {
       Leaf { .. } => 1,
+      Chunk(xs) => xs.len(),
       Branch2 { size, .. } => *size,
       Branch3 { size, .. } => *size,
     }
@@ -50,94 +78,6 @@ where
     false
   }
 
-  /// make list again from existed
-  /// use a factor to control side branches to be shallow with smaller depth
-  /// root node has a factor of 2
-  pub fn rebuild_list(size: usize, offset: usize, xs: &[TernaryTree<T>], factor: u8) -> Self {
-    match size {
-      0 => unreachable!("Does not work for empty list"),
-      1 => xs[offset].to_owned(),
-      2 => Self::rebuild_list_side(size, offset, xs),
-      3 => Self::rebuild_list_side(size, offset, xs),
-      _ => {
-        let side_capacity = triple_size(factor - 1);
-        if side_capacity * 2 < size {
-          let divided = (side_capacity, size - side_capacity - side_capacity, side_capacity);
-
-          let left = Self::rebuild_list_side(divided.0, offset, xs);
-          let middle = Self::rebuild_list(divided.1, offset + divided.0, xs, factor + 1);
-          let right = Self::rebuild_list_side(divided.2, offset + divided.0 + divided.1, xs);
-
-          Branch3 {
-            size,
-            left: Arc::new(left),
-            middle: Arc::new(middle),
-            right: Arc::new(right),
-          }
-        } else {
-          Self::rebuild_list_side(size, offset, xs)
-        }
-      }
-    }
-  }
-
-  // sides have different algorithm
-  pub fn rebuild_list_side(size: usize, offset: usize, xs: &[TernaryTree<T>]) -> Self {
-    match size {
-      0 => unreachable!("Does not work for empty list"),
-      1 => xs[offset].to_owned(),
-      2 => {
-        let left = &xs[offset];
-        let middle = &xs[offset + 1];
-        Branch2 {
-          size: left.len() + middle.len(),
-          left: Arc::new(left.to_owned()),
-          middle: Arc::new(middle.to_owned()),
-        }
-      }
-      3 => {
-        let left = &xs[offset];
-        let middle = &xs[offset + 1];
-        let right = &xs[offset + 2];
-        Branch3 {
-          size: 3,
-          left: Arc::new(left.to_owned()),
-          middle: Arc::new(middle.to_owned()),
-          right: Arc::new(right.to_owned()),
-        }
-      }
-      _ => {
-        let divided = divide_ternary_sizes(size);
-
-        let left = Self::rebuild_list_side(divided.0, offset, xs);
-        let middle = Self::rebuild_list_side(divided.1, offset + divided.0, xs);
-        let right = Self::rebuild_list_side(divided.2, offset + divided.0 + divided.1, xs);
-
-        Branch3 {
-          size: left.len() + middle.len() + right.len(),
-          left: Arc::new(left),
-          middle: Arc::new(middle),
-          right: Arc::new(right),
-        }
-      }
-    }
-  }
-
-  /// turn into a representation in triples, with `_` for holes
-  pub fn format_inline(&self) -> String {
-    match self {
-      Leaf(value) => value.to_string(),
-      Branch2 { left, middle, .. } => {
-        // TODO maybe need more informations here
-        format!("({} {})", left.format_inline(), middle.format_inline())
-      }
-      Branch3 { left, middle, right, .. } => {
-        // TODO maybe need more informations here
-        format!("({} {} {})", left.format_inline(), middle.format_inline(), right.format_inline())
-      }
-    }
-  }
-
   pub fn find_index(&self, f: Arc<dyn Fn(&T) -> bool>) -> Option<i64> {
     match self {
       Leaf(value) => {
@@ -147,6 +87,7 @@ where
           None
         }
       }
+      Chunk(xs) => xs.iter().position(|x| f(x)).map(|pos| pos as i64),
 
       Branch2 { left, middle, .. } => left
         .find_index(f.to_owned())
@@ -163,82 +104,6 @@ where
     }
   }
 
-  pub fn index_of(&self, item: &T) -> Option<usize> {
-    match self {
-      Leaf(value) => {
-        if item == value {
-          Some(0)
-        } else {
-          None
-        }
-      }
-      Branch2 { left, middle, .. } => left.index_of(item).or_else(|| middle.index_of(item).map(|pos| pos + left.len())),
-      Branch3 { left, middle, right, .. } => left
-        .index_of(item)
-        .or_else(|| middle.index_of(item).map(|pos| pos + left.len()))
-        .or_else(|| right.index_of(item).map(|pos| pos + left.len() + middle.len())),
-    }
-  }
-
-  // index from end, returns 0 when item found at end of original list
-  pub fn last_index_of(&self, item: &T) -> Option<usize> {
-    match self {
-      Leaf(value) => {
-        if item == value {
-          Some(0)
-        } else {
-          None
-        }
-      }
-      Branch2 { left, middle, .. } => middle
-        .last_index_of(item)
-        .or_else(|| left.last_index_of(item).map(|pos| pos + middle.len())),
-      Branch3 { left, middle, right, .. } => right
-        .last_index_of(item)
-        .or_else(|| middle.last_index_of(item).map(|pos| pos + right.len()))
-        .or_else(|| left.last_index_of(item).map(|pos| pos + middle.len() + right.len())),
-    }
-  }
-
-  /// recursively check structure
-  pub fn eq_shape(&self, ys: &Self) -> bool {
-    if self.len() != ys.len() {
-      return false;
-    }
-
-    match (self, ys) {
-      (Leaf(value), Leaf(v2)) => value == v2,
-      (
-        Branch2 { left, middle, .. },
-        Branch2 {
-          left: left2,
-          middle: middle2,
-          ..
-        },
-      ) => left.eq_shape(left2) && middle.eq_shape(middle2),
-      (
-        Branch3 { left, middle, right, .. },
-        Branch3 {
-          left: left2,
-          middle: middle2,
-          right: right2,
-          ..
-        },
-      ) => left.eq_shape(left2) && middle.eq_shape(middle2) && right.eq_shape(right2),
-
-      (_, _) => false,
-    }
-  }
-
-  /// internal usages for rebuilding tree
-  fn to_leaves(&self) -> Vec<TernaryTree<T>> {
-    let mut acc: Vec<TernaryTree<T>> = Vec::with_capacity(self.len());
-    let counter: Cell<usize> = Cell::new(0);
-    write_leaves(self, &mut acc, &counter);
-    assert_eq!(acc.len(), self.len());
-    acc
-  }
-
   /// get with reference, but index is not checked, returns last element if too large
   pub fn ref_get(&self, idx: usize) -> &T {
     // println!("get: {} {}", self.format_inline(), idx);
@@ -268,12 +133,13 @@ where
         }
       }
       Leaf(value) => value,
+      Chunk(xs) => &xs[idx.min(xs.len() - 1)],
     }
   }
 
   /// get am element via drilling down the branch with a mutable loop,
   /// supposed to be faster than `ref_get` since it's more like VM instructions
-  pub fn loop_get(&'a self, original_idx: usize) -> &'a T {
+  pub fn loop_get(&self, original_idx: usize) -> &T {
     let mut tree_parent = self;
     let mut idx = original_idx;
     loop {
@@ -281,6 +147,9 @@ where
         Leaf(value) => {
           return value;
         }
+        Chunk(xs) => {
+          return &xs[idx.min(xs.len() - 1)];
+        }
         Branch2 { left, middle, .. } => {
           let left_size = left.len();
           if idx < left_size {
@@ -312,31 +181,66 @@ where
     }
   }
 
-  pub fn loop_first(&'a self) -> &'a T {
+  pub fn loop_first(&self) -> &T {
     let mut tree_parent = self;
     loop {
       match tree_parent {
         Leaf(value) => return value,
+        Chunk(xs) => return &xs[0],
         Branch2 { left, .. } => tree_parent = left,
         Branch3 { left, .. } => tree_parent = left,
       }
     }
   }
 
-  pub fn loop_last(&'a self) -> &'a T {
+  pub fn loop_last(&self) -> &T {
     let mut tree_parent = self;
     loop {
       match tree_parent {
         Leaf(value) => return value,
+        Chunk(xs) => return &xs[xs.len() - 1],
         Branch2 { middle, .. } => tree_parent = middle,
         Branch3 { right, .. } => tree_parent = right,
       }
     }
   }
 
+  /// levels of branches above the deepest leaf or chunk, cached in branches after first computed
+  fn depth(&self) -> usize {
+    match self {
+      Leaf(_) | Chunk(_) => 0,
+      Branch2 { left, middle, cache, .. } => cache.depth(|| 1 + left.depth().max(middle.depth())),
+      Branch3 {
+        left,
+        middle,
+        right,
+        cache,
+        ..
+      } => cache.depth(|| 1 + left.depth().max(middle.depth()).max(right.depth())),
+    }
+  }
+
+  /// leaf or chunk at the start
+  fn first_leaf(&self) -> &Self {
+    match self {
+      Branch2 { left, .. } | Branch3 { left, .. } => left.first_leaf(),
+      _ => self,
+    }
+  }
+
+  /// leaf or chunk at the end
+  fn last_leaf(&self) -> &Self {
+    match self {
+      Branch2 { middle, .. } => middle.last_leaf(),
+      Branch3 { right, .. } => right.last_leaf(),
+      _ => self,
+    }
+  }
+
   pub fn first(&self) -> Option<&T> {
     match self {
       Leaf(value) => Some(value),
+      Chunk(xs) => xs.first(),
       Branch2 { left, .. } => left.first(),
       Branch3 { left, .. } => left.first(),
     }
@@ -345,19 +249,184 @@ where
   pub fn last(&self) -> Option<&T> {
     match self {
       Leaf(value) => Some(value),
+      Chunk(xs) => xs.last(),
       Branch2 { middle, .. } => middle.last(),
       Branch3 { right, .. } => right.last(),
     }
   }
 
+  pub fn check_structure(&self) -> Result<(), TernaryTreeError> {
+    match self {
+      Leaf { .. } => Ok(()),
+      Chunk(xs) => {
+        if xs.is_empty() || xs.len() > CHUNK_SIZE {
+          Err(TernaryTreeError::InvalidChunk { size: xs.len() })
+        } else {
+          Ok(())
+        }
+      }
+      Branch2 { left, middle, size, .. } => {
+        if *size != left.len() + middle.len() {
+          return Err(TernaryTreeError::SizeMismatch {
+            expected: *size,
+            actual: left.len() + middle.len(),
+          });
+        }
+
+        left.check_structure()?;
+        middle.check_structure()?;
+
+        Ok(())
+      }
+      Branch3 {
+        left, middle, right, size, ..
+      } => {
+        if *size != left.len() + middle.len() + right.len() {
+          return Err(TernaryTreeError::SizeMismatch {
+            expected: *size,
+            actual: left.len() + middle.len() + right.len(),
+          });
+        }
+
+        left.check_structure()?;
+        middle.check_structure()?;
+        right.check_structure()?;
+
+        Ok(())
+      }
+    }
+  }
+
+  pub fn map<V>(&self, f: Arc<dyn Fn(&T) -> V>) -> TernaryTree<V> {
+    match self {
+      Leaf(value) => Leaf(f(value)),
+      Chunk(xs) => Chunk(xs.iter().map(|x| f(x)).collect()),
+      Branch2 { left, middle, size, .. } => Branch2 {
+        size: *size,
+        left: Arc::new(left.map(f.clone())),
+        middle: Arc::new(middle.map(f.clone())),
+        cache: NodeCache::new(),
+      },
+      Branch3 {
+        left, middle, right, size, ..
+      } => Branch3 {
+        size: *size,
+        left: Arc::new(left.map(f.clone())),
+        middle: Arc::new(middle.map(f.clone())),
+        right: Arc::new(right.map(f.clone())),
+        cache: NodeCache::new(),
+      },
+    }
+  }
+
+  pub fn traverse(&self, f: &mut dyn FnMut(&T)) {
+    match self {
+      Leaf(value) => f(value),
+      Chunk(xs) => xs.iter().for_each(f),
+      Branch2 { left, middle, .. } => {
+        left.traverse(f);
+        middle.traverse(f);
+      }
+      Branch3 { left, middle, right, .. } => {
+        left.traverse(f);
+        middle.traverse(f);
+        right.traverse(f);
+      }
+    }
+  }
+
+  pub fn traverse_result<S>(&self, f: &mut dyn FnMut(&T) -> Result<(), S>) -> Result<(), S> {
+    match self {
+      Leaf(value) => f(value),
+      Chunk(xs) => xs.iter().try_for_each(f),
+      Branch2 { left, middle, .. } => {
+        left.traverse_result(f)?;
+        middle.traverse_result(f)?;
+        Ok(())
+      }
+      Branch3 { left, middle, right, .. } => {
+        left.traverse_result(f)?;
+        middle.traverse_result(f)?;
+        right.traverse_result(f)?;
+        Ok(())
+      }
+    }
+  }
+
+  pub fn iter(&self) -> TernaryTreeIterator<'_, T> {
+    TernaryTreeIterator::new(self)
+  }
+
+  /// iterate over elements in `start..end`, range is not checked
+  pub fn iter_range(&self, start: usize, end: usize) -> TernaryTreeIterator<'_, T> {
+    TernaryTreeIterator::new_range(self, start, end)
+  }
+}
+
+impl<T: Clone> TernaryTree<T> {
+  /// make list again from existed
+  /// use a factor to control side branches to be shallow with smaller depth
+  /// root node has a factor of 2
+  pub fn rebuild_list(size: usize, offset: usize, xs: &[TernaryTree<T>], factor: u8) -> Self {
+    let nodes: Vec<Arc<Self>> = xs[offset..offset + size].iter().map(|x| Arc::new(x.to_owned())).collect();
+    Arc::unwrap_or_clone(rebuild_nodes(&nodes, factor))
+  }
+
+  // sides have different algorithm
+  pub fn rebuild_list_side(size: usize, offset: usize, xs: &[TernaryTree<T>]) -> Self {
+    let nodes: Vec<Arc<Self>> = xs[offset..offset + size].iter().map(|x| Arc::new(x.to_owned())).collect();
+    Arc::unwrap_or_clone(rebuild_nodes_side(&nodes))
+  }
+
+  /// pack elements into chunks of `CHUNK_SIZE`, the last chunk may be shorter
+  pub fn chunk_leaves<I: IntoIterator<Item = T>>(items: I) -> Vec<Self> {
+    let mut chunks: Vec<Self> = vec![];
+    let mut current: Vec<T> = Vec::with_capacity(CHUNK_SIZE);
+    for item in items {
+      current.push(item);
+      if current.len() == CHUNK_SIZE {
+        chunks.push(Chunk(current));
+        current = Vec::with_capacity(CHUNK_SIZE);
+      }
+    }
+    if !current.is_empty() {
+      chunks.push(Chunk(current));
+    }
+    chunks
+  }
+
+  /// same shape as `rebuild_list` on all of `xs` with a factor of 2, leaves are moved into the tree without cloning elements
+  pub fn rebuild_owned(xs: Vec<TernaryTree<T>>) -> Self {
+    let nodes: Vec<Arc<Self>> = xs.into_iter().map(Arc::new).collect();
+    Arc::unwrap_or_clone(rebuild_nodes(&nodes, 2))
+  }
+
+  /// internal usages for rebuilding tree, chunks are kept as leaves
+  fn to_leaves(&self) -> Vec<TernaryTree<T>> {
+    let mut acc: Vec<TernaryTree<T>> = Vec::with_capacity(self.len());
+    let counter: Cell<usize> = Cell::new(0);
+    write_leaves(self, &mut acc, &counter);
+    assert_eq!(acc.len(), counter.get());
+    acc
+  }
+
   // insert new element at position, does not check whether the index is out of bound
-  pub fn assoc(&self, idx: usize, item: T) -> Result<Self, String> {
+  pub fn assoc(&self, idx: usize, item: T) -> Result<Self, TernaryTreeError> {
     match self {
       Leaf { .. } => {
         if idx == 0 {
           Ok(Leaf(item))
         } else {
-          Err(format!("Cannot assoc leaf into index {idx}"))
+          Err(TernaryTreeError::IndexOutOfBounds { index: idx, len: 1 })
+        }
+      }
+      Chunk(xs) => {
+        if idx < xs.len() {
+          let mut ys = xs.to_owned();
+          ys[idx] = item;
+          Ok(Chunk(ys))
+        } else {
+          Err(TernaryTreeError::IndexOutOfBounds { index: idx, len: xs.len() })
         }
       }
       Branch2 { left, middle, size, .. } => {
@@ -367,6 +436,7 @@ where
             size: size.to_owned(),
             left: Arc::new(changed_branch),
             middle: middle.to_owned(),
+            cache: NodeCache::new(),
           })
         } else {
           let changed_branch = middle.assoc(idx - left.len(), item)?;
@@ -374,6 +444,7 @@ where
             size: size.to_owned(),
             left: left.to_owned(),
             middle: Arc::new(changed_branch),
+            cache: NodeCache::new(),
           })
         }
       }
@@ -387,6 +458,7 @@ where
             left: Arc::new(changed_branch),
             middle: middle.to_owned(),
             right: right.to_owned(),
+            cache: NodeCache::new(),
           })
         } else if idx < left.len() + middle.len() {
           let changed_branch = middle.assoc(idx - left.len(), item)?;
@@ -395,6 +467,7 @@ where
             left: left.to_owned(),
             middle: Arc::new(changed_branch),
             right: right.to_owned(),
+            cache: NodeCache::new(),
           })
         } else {
           let changed_branch = right.assoc(idx - left.len() - middle.len(), item)?;
@@ -403,6 +476,7 @@ where
             left: left.to_owned(),
             middle: middle.to_owned(),
             right: Arc::new(changed_branch),
+            cache: NodeCache::new(),
           })
         }
       }
@@ -410,30 +484,43 @@ where
   }
 
   // remove element from give position, does not check whether the index is out of bound
-  pub fn dissoc(&self, idx: usize) -> Result<Self, String> {
+  pub fn dissoc(&self, idx: usize) -> Result<Self, TernaryTreeError> {
     match self {
       Leaf { .. } => unreachable!("dissoc should be handled at branches"),
+      Chunk(xs) => {
+        if idx < xs.len() {
+          let mut ys = xs.to_owned();
+          ys.remove(idx);
+          Ok(Chunk(ys))
+        } else {
+          Err(TernaryTreeError::IndexOutOfBounds { index: idx, len: xs.len() })
+        }
+      }
       Branch2 { left, middle, size, .. } => {
         if idx < left.len() {
           if left.len() == 1 {
-            Ok((**middle).to_owned())
+            Ok((**middle).to_owned().balance_edited())
           } else {
             let changed_branch = left.dissoc(idx)?;
-            Ok(Branch2 {
+            let branch = Branch2 {
               size: *size - 1,
               left: Arc::new(changed_branch),
               middle: middle.to_owned(),
-            })
+              cache: NodeCache::new(),
+            };
+            Ok(branch.balance_edited())
           }
         } else if middle.len() == 1 {
-          Ok((**left).to_owned())
+          Ok((**left).to_owned().balance_edited())
         } else {
           let changed_branch = middle.dissoc(idx - left.len())?;
-          Ok(Branch2 {
+          let branch = Branch2 {
             size: *size - 1,
             left: left.to_owned(),
             middle: Arc::new(changed_branch),
-          })
+            cache: NodeCache::new(),
+          };
+          Ok(branch.balance_edited())
         }
       }
 
@@ -441,85 +528,104 @@ where
         left, middle, right, size, ..
       } => {
         if left.len() + middle.len() + right.len() != *size {
-          return Err(format!(
-            "tree {} does not match sum from branch sizes {}",
-            self.format_inline(),
-            self.len()
-          ));
+          return Err(TernaryTreeError::SizeMismatch {
+            expected: *size,
+            actual: left.len() + middle.len() + right.len(),
+          });
         }
 
         if idx < left.len() {
           if left.len() == 1 {
-            Ok(Branch2 {
+            let branch = Branch2 {
               size: *size - 1,
               left: middle.to_owned(),
               middle: right.to_owned(),
-            })
+              cache: NodeCache::new(),
+            };
+            Ok(branch.balance_edited())
           } else {
             let changed_branch = left.dissoc(idx)?;
-            Ok(Branch3 {
+            let branch = Branch3 {
               size: *size - 1,
               left: Arc::new(changed_branch),
               middle: middle.to_owned(),
               right: right.to_owned(),
-            })
+              cache: NodeCache::new(),
+            };
+            Ok(branch.balance_edited())
           }
         } else if idx < left.len() + middle.len() {
           if middle.len() == 1 {
-            Ok(Branch2 {
+            let branch = Branch2 {
               size: *size - 1,
               left: left.to_owned(),
               middle: right.to_owned(),
-            })
+              cache: NodeCache::new(),
+            };
+            Ok(branch.balance_edited())
           } else {
             let changed_branch = middle.dissoc(idx - left.len())?;
-            Ok(Branch3 {
+            let branch = Branch3 {
               size: *size - 1,
               left: left.to_owned(),
               middle: Arc::new(changed_branch),
               right: right.to_owned(),
-            })
+              cache: NodeCache::new(),
+            };
+            Ok(branch.balance_edited())
           }
         } else if right.len() == 1 {
-          Ok(Branch2 {
+          let branch = Branch2 {
             size: *size - 1,
             left: left.to_owned(),
             middle: middle.to_owned(),
-          })
+            cache: NodeCache::new(),
+          };
+          Ok(branch.balance_edited())
         } else {
           let changed_branch = right.dissoc(idx - left.len() - middle.len())?;
-          Ok(Branch3 {
+          let branch = Branch3 {
             size: *size - 1,
             left: left.to_owned(),
             middle: middle.to_owned(),
             right: Arc::new(changed_branch),
-          })
+            cache: NodeCache::new(),
+          };
+          Ok(branch.balance_edited())
         }
       }
     }
   }
-  pub fn rest(&self) -> Result<Self, String> {
+
+  pub fn rest(&self) -> Result<Self, TernaryTreeError> {
     if self.len() == 1 {
-      Err(String::from("unexpected leaf for rest"))
+      Err(TernaryTreeError::EmptyList)
     } else {
       self.dissoc(0)
     }
   }
-  pub fn butlast(&self) -> Result<Self, String> {
+
+  pub fn butlast(&self) -> Result<Self, TernaryTreeError> {
     if self.len() == 1 {
-      Err(String::from("unexpected leaf for butlast"))
+      Err(TernaryTreeError::EmptyList)
     } else {
       self.dissoc(self.len() - 1)
     }
   }
 
-  pub fn insert_before(&self, idx: usize, item: T) -> Result<Self, String> {
+  /// branches on the edited path are rebuilt when they get too deep for their sizes
+  pub fn insert_before(&self, idx: usize, item: T) -> Result<Self, TernaryTreeError> {
     match self {
-      Leaf { .. } => Ok(Branch2 {
-        size: 2,
-        left: Arc::new(Leaf(item)),
-        middle: Arc::new(self.to_owned()),
-      }),
+      Leaf { .. } => {
+        let branch = Branch2 {
+          size: 2,
+          left: Arc::new(Leaf(item)),
+          middle: Arc::new(self.to_owned()),
+          cache: NodeCache::new(),
+        };
+        Ok(branch)
+      }
+      Chunk(xs) => chunk_insert(xs, idx, item, false),
 
       Branch2 { left, middle, size, .. } => {
         if *size < 2 {
@@ -528,54 +634,67 @@ where
 
         if *size == 2 {
           if idx == 0 {
-            return Ok(Branch3 {
+            let branch = Branch3 {
               size: 3,
               left: Arc::new(Leaf(item)),
               middle: left.to_owned(),
               right: middle.to_owned(),
-            });
+              cache: NodeCache::new(),
+            };
+            return Ok(branch);
           } else if idx == 1 {
-            return Ok(Branch3 {
+            let branch = Branch3 {
               size: 3,
               left: left.to_owned(),
               middle: Arc::new(Leaf(item)),
               right: middle.to_owned(),
-            });
+              cache: NodeCache::new(),
+            };
+            return Ok(branch);
           } else {
-            return Err(String::from("cannot insert before position 2 since only 2 elements here"));
+            return Err(TernaryTreeError::IndexOutOfBounds { index: idx, len: 2 });
           }
         }
 
         if left.len() + middle.len() != *size {
-          return Err(String::from("tree.size does not match sum case branch sizes"));
+          return Err(TernaryTreeError::SizeMismatch {
+            expected: *size,
+            actual: left.len() + middle.len(),
+          });
         }
 
         // echo "picking: ", idx, " ", left.len(), " ", middle.len(), " ", right.len()
 
         if idx == 0 {
-          return Ok(Branch3 {
+          let branch = Branch3 {
             size: *size + 1,
             left: Arc::new(Leaf(item)),
             middle: left.to_owned(),
             right: middle.to_owned(),
-          });
+            cache: NodeCache::new(),
+          };
+          return Ok(branch);
         }
 
         if idx < left.len() {
           let changed_branch = left.insert_before(idx, item)?;
-          Ok(Branch2 {
+          let branch = Branch2 {
             size: *size + 1,
             left: Arc::new(changed_branch),
             middle: middle.to_owned(),
-          })
+            cache: NodeCache::new(),
+          };
+          Ok(branch.balance_edited())
         } else {
           let changed_branch = middle.insert_before(idx - left.len(), item)?;
 
-          Ok(Branch2 {
+          let branch = Branch2 {
             size: *size + 1,
             left: left.to_owned(),
             middle: Arc::new(changed_branch),
-          })
+            cache: NodeCache::new(),
+          };
+          Ok(branch.balance_edited())
         }
       }
       Branch3 {
@@ -586,66 +705,85 @@ where
         }
 
         if left.len() + middle.len() + right.len() != *size {
-          return Err(String::from("tree.size does not match sum case branch sizes"));
+          return Err(TernaryTreeError::SizeMismatch {
+            expected: *size,
+            actual: left.len() + middle.len() + right.len(),
+          });
         }
 
         // echo "picking: ", idx, " ", left.len(), " ", middle.len(), " ", right.len()
 
         if idx == 0 && left.len() >= middle.len() && left.len() >= right.len() {
-          return Ok(Branch2 {
+          let branch = Branch2 {
             size: *size + 1,
             left: Arc::new(Leaf(item)),
             middle: Arc::new(self.to_owned()),
-          });
+            cache: NodeCache::new(),
+          };
+          return Ok(branch.balance_edited());
         }
 
         if idx == 0 && right.is_empty() && middle.len() >= right.len() {
-          return Ok(Branch3 {
+          let branch = Branch3 {
             size: *size + 1,
             left: Arc::new(Leaf(item)),
             middle: left.to_owned(),
             right: middle.to_owned(),
-          });
+            cache: NodeCache::new(),
+          };
+          return Ok(branch);
         }
 
         if idx < left.len() {
           let changed_branch = left.insert_before(idx, item)?;
-          Ok(Branch3 {
+          let branch = Branch3 {
             size: *size + 1,
             left: Arc::new(changed_branch),
             middle: middle.to_owned(),
             right: right.to_owned(),
-          })
+            cache: NodeCache::new(),
+          };
+          Ok(branch.balance_edited())
         } else if idx < left.len() + middle.len() {
           let changed_branch = middle.insert_before(idx - left.len(), item)?;
 
-          Ok(Branch3 {
+          let branch = Branch3 {
             size: *size + 1,
             left: left.to_owned(),
             middle: Arc::new(changed_branch),
             right: right.to_owned(),
-          })
+            cache: NodeCache::new(),
+          };
+          Ok(branch.balance_edited())
         } else {
           let changed_branch = right.insert_before(idx - left.len() - middle.len(), item)?;
 
-          Ok(Branch3 {
+          let branch = Branch3 {
             size: *size + 1,
             left: left.to_owned(),
             middle: middle.to_owned(),
             right: Arc::new(changed_branch),
-          })
+            cache: NodeCache::new(),
+          };
+          Ok(branch.balance_edited())
         }
       }
     }
   }
 
-  pub fn insert_after(&self, idx: usize, item: T) -> Result<Self, String> {
+  /// branches on the edited path are rebuilt when they get too deep for their sizes
+  pub fn insert_after(&self, idx: usize, item: T) -> Result<Self, TernaryTreeError> {
     match self {
-      Leaf { .. } => Ok(Branch2 {
-        size: 2,
-        left: Arc::new(self.to_owned()),
-        middle: Arc::new(Leaf(item)),
-      }),
+      Leaf { .. } => {
+        let branch = Branch2 {
+          size: 2,
+          left: Arc::new(self.to_owned()),
+          middle: Arc::new(Leaf(item)),
+          cache: NodeCache::new(),
+        };
+        Ok(branch)
+      }
+      Chunk(xs) => chunk_insert(xs, idx, item, true),
 
       Branch2 { left, middle, size, .. } => {
         if *size < 2 {
@@ -654,55 +792,68 @@ where
 
         if *size == 2 {
           if idx == 0 {
-            return Ok(Branch3 {
+            let branch = Branch3 {
               size: 3,
               left: left.to_owned(),
               middle: Arc::new(Leaf(item)),
               right: middle.to_owned(),
-            });
+              cache: NodeCache::new(),
+            };
+            return Ok(branch);
           }
           if idx == 1 {
-            return Ok(Branch3 {
+            let branch = Branch3 {
               size: 3,
               left: left.to_owned(),
               middle: middle.to_owned(),
               right: Arc::new(Leaf(item)),
-            });
+              cache: NodeCache::new(),
+            };
+            return Ok(branch);
           } else {
-            return Err(String::from("cannot insert after position 2 since only 2 elements here"));
+            return Err(TernaryTreeError::IndexOutOfBounds { index: idx, len: 2 });
           }
         }
 
         if left.len() + middle.len() != *size {
-          return Err(String::from("tree.size does not match sum case branch sizes"));
+          return Err(TernaryTreeError::SizeMismatch {
+            expected: *size,
+            actual: left.len() + middle.len(),
+          });
         }
 
         // echo "picking: ", idx, " ", left.len(), " ", middle.len(), " ", right.len()
 
         if idx == *size - 1 {
-          return Ok(Branch3 {
+          let branch = Branch3 {
             size: *size + 1,
             left: left.to_owned(),
             middle: middle.to_owned(),
             right: Arc::new(Leaf(item)),
-          });
+            cache: NodeCache::new(),
+          };
+          return Ok(branch);
         }
 
         if idx < left.len() {
           let changed_branch = left.insert_after(idx, item)?;
-          Ok(Branch2 {
+          let branch = Branch2 {
             size: *size + 1,
             left: Arc::new(changed_branch),
             middle: middle.to_owned(),
-          })
+            cache: NodeCache::new(),
+          };
+          Ok(branch.balance_edited())
         } else {
           let changed_branch = middle.insert_after(idx - left.len(), item)?;
 
-          Ok(Branch2 {
+          let branch = Branch2 {
             size: *size + 1,
             left: left.to_owned(),
             middle: Arc::new(changed_branch),
-          })
+            cache: NodeCache::new(),
+          };
+          Ok(branch.balance_edited())
         }
       }
       Branch3 {
@@ -713,81 +864,109 @@ where
         }
 
         if left.len() + middle.len() + right.len() != *size {
-          return Err(String::from("tree.size does not match sum case branch sizes"));
+          return Err(TernaryTreeError::SizeMismatch {
+            expected: *size,
+            actual: left.len() + middle.len() + right.len(),
+          });
         }
 
         // echo "picking: ", idx, " ", left.len(), " ", middle.len(), " ", right.len()
 
         if idx == *size - 1 && right.len() >= middle.len() && right.len() >= left.len() {
-          return Ok(Branch2 {
+          let branch = Branch2 {
             size: *size + 1,
             left: Arc::new(self.to_owned()),
             middle: Arc::new(Leaf(item)),
-          });
+            cache: NodeCache::new(),
+          };
+          return Ok(branch.balance_edited());
         }
 
         if idx == *size - 1 && right.is_empty() && middle.len() >= left.len() {
-          return Ok(Branch3 {
+          let branch = Branch3 {
             size: *size + 1,
             left: left.to_owned(),
             middle: middle.to_owned(),
             right: Arc::new(Leaf(item)),
-          });
+            cache: NodeCache::new(),
+          };
+          return Ok(branch);
         }
 
         if idx < left.len() {
           let changed_branch = left.insert_after(idx, item)?;
-          Ok(Branch3 {
+          let branch = Branch3 {
             size: *size + 1,
             left: Arc::new(changed_branch),
             middle: middle.to_owned(),
             right: right.to_owned(),
-          })
+            cache: NodeCache::new(),
+          };
+          Ok(branch.balance_edited())
         } else if idx < left.len() + middle.len() {
           let changed_branch = middle.insert_after(idx - left.len(), item)?;
 
-          Ok(Branch3 {
+          let branch = Branch3 {
             size: *size + 1,
             left: left.to_owned(),
             middle: Arc::new(changed_branch),
             right: right.to_owned(),
-          })
+            cache: NodeCache::new(),
+          };
+          Ok(branch.balance_edited())
         } else {
           let changed_branch = right.insert_after(idx - left.len() - middle.len(), item)?;
 
-          Ok(Branch3 {
+          let branch = Branch3 {
             size: *size + 1,
             left: left.to_owned(),
             middle: middle.to_owned(),
             right: Arc::new(changed_branch),
-          })
+            cache: NodeCache::new(),
+          };
+          Ok(branch.balance_edited())
         }
       }
     }
   }
 
-  pub fn assoc_before(&self, idx: usize, item: T) -> Result<Self, String> {
+  pub fn assoc_before(&self, idx: usize, item: T) -> Result<Self, TernaryTreeError> {
     self.insert_before(idx, item)
   }
-  pub fn assoc_after(&self, idx: usize, item: T) -> Result<Self, String> {
+
+  pub fn assoc_after(&self, idx: usize, item: T) -> Result<Self, TernaryTreeError> {
     self.insert_after(idx, item)
   }
+
   // this function mutates original tree to make it more balanced
-  pub fn force_inplace_balancing(&mut self) -> Result<(), String> {
+  pub fn force_inplace_balancing(&mut self) -> Result<(), TernaryTreeError> {
     let ys = self.to_leaves();
     *self = Self::rebuild_list(ys.len(), 0, &ys, 2);
     Ok(())
   }
 
+  /// rebuild an edited branch into a balanced one when it gets too deep for its size.
+  /// depths are cached in branches, so only new branches on the edited path are visited
+  fn balance_edited(self) -> Self {
+    if self.depth() > depth_limit(self.len()) {
+      let ys = self.to_leaves();
+      Self::rebuild_list_side(ys.len(), 0, &ys)
+    } else {
+      self
+    }
+  }
+
   pub fn unshift(&self, item: T) -> Self {
     self.prepend(item)
   }
+
   pub fn prepend(&self, item: T) -> Self {
     match self.insert_before(0, item) {
       Ok(v) => v,
       Err(e) => unreachable!("{}", e),
     }
   }
+
   pub fn concat(raw: &[TernaryTree<T>]) -> Self {
     if raw.is_empty() {
       unreachable!("concat requires at least one non-empty tree");
@@ -799,6 +978,117 @@ where
     Self::concat_layers(&mut ys)
   }
 
+  /// room left in the last chunk is filled first, the rest are packed into chunks and joined with `append_tree`
+  pub fn append_iter<I: IntoIterator<Item = T>>(&self, items: I) -> Self {
+    let mut items = items.into_iter();
+    let filled = match self.last_leaf() {
+      Chunk(xs) if xs.len() < CHUNK_SIZE => {
+        let head: Vec<T> = items.by_ref().take(CHUNK_SIZE - xs.len()).collect();
+        if head.is_empty() {
+          return self.to_owned();
+        }
+        self.push_right_chunk(head.into_iter())
+      }
+      _ => self.to_owned(),
+    };
+    let ys = Self::chunk_leaves(items);
+    if ys.is_empty() {
+      filled
+    } else {
+      filled.append_tree(Self::rebuild_owned(ys))
+    }
+  }
+
+  /// put `tail` at the end, it is joined down the right side at a branch about as deep as `tail`,
+  /// so only branches on that side are created again, and they are rebuilt when too deep for their sizes
+  pub fn append_tree(&self, tail: Self) -> Self {
+    match self.append_tree_split(tail) {
+      (joined, None) => joined,
+      (left, Some(middle)) => Branch2 {
+        size: left.len() + middle.len(),
+        left: Arc::new(left),
+        middle: Arc::new(middle),
+        cache: NodeCache::new(),
+      }
+      .balance_edited(),
+    }
+  }
+
+  /// joins like inserting into a 2-3 tree, returns a second branch when the joined branch has no room for one more child
+  fn append_tree_split(&self, tail: Self) -> (Self, Option<Self>) {
+    if self.depth() <= tail.depth() {
+      return (self.to_owned(), Some(tail));
+    }
+    match self {
+      Leaf(_) | Chunk(_) => unreachable!("leaves are never deeper than tail"),
+      Branch2 { left, middle, .. } => match middle.append_tree_split(tail) {
+        (joined, None) => {
+          let branch = Branch2 {
+            size: left.len() + joined.len(),
+            left: left.to_owned(),
+            middle: Arc::new(joined),
+            cache: NodeCache::new(),
+          };
+          (branch.balance_edited(), None)
+        }
+        (joined, Some(extra)) => {
+          let branch = Branch3 {
+            size: left.len() + joined.len() + extra.len(),
+            left: left.to_owned(),
+            middle: Arc::new(joined),
+            right: Arc::new(extra),
+            cache: NodeCache::new(),
+          };
+          (branch.balance_edited(), None)
+        }
+      },
+      Branch3 { left, middle, right, .. } => match right.append_tree_split(tail) {
+        (joined, None) => {
+          let branch = Branch3 {
+            size: left.len() + middle.len() + joined.len(),
+            left: left.to_owned(),
+            middle: middle.to_owned(),
+            right: Arc::new(joined),
+            cache: NodeCache::new(),
+          };
+          (branch.balance_edited(), None)
+        }
+        (joined, Some(extra)) if joined.depth().max(extra.depth()) + 1 < self.depth() => {
+          // the right side is shallower than this branch, so there is still room below
+          let pair = Branch2 {
+            size: joined.len() + extra.len(),
+            left: Arc::new(joined),
+            middle: Arc::new(extra),
+            cache: NodeCache::new(),
+          };
+          let branch = Branch3 {
+            size: left.len() + middle.len() + pair.len(),
+            left: left.to_owned(),
+            middle: middle.to_owned(),
+            right: Arc::new(pair),
+            cache: NodeCache::new(),
+          };
+          (branch.balance_edited(), None)
+        }
+        (joined, Some(extra)) => {
+          let first = Branch2 {
+            size: left.len() + middle.len(),
+            left: left.to_owned(),
+            middle: middle.to_owned(),
+            cache: NodeCache::new(),
+          };
+          let second = Branch2 {
+            size: joined.len() + extra.len(),
+            left: Arc::new(joined),
+            middle: Arc::new(extra),
+            cache: NodeCache::new(),
+          };
+          (first, Some(second))
+        }
+      },
+    }
+  }
+
   /// This was the old implementation of concat. It is not balanced and does not work with empty lists.
   pub fn concat_dumb(raw: &[TernaryTree<T>]) -> Self {
     let mut xs_groups: Vec<TernaryTree<T>> = Vec::with_capacity(raw.len());
@@ -815,6 +1105,7 @@ where
           size: left.len() + middle.len(),
           left: Arc::new(left),
           middle: Arc::new(middle),
+          cache: NodeCache::new(),
         }
       }
       3 => {
@@ -826,6 +1117,7 @@ where
           left: Arc::new(left),
           middle: Arc::new(middle),
           right: Arc::new(right),
+          cache: NodeCache::new(),
         }
       }
       _ => {
@@ -857,6 +1149,7 @@ where
             left: Arc::new(left),
             middle: Arc::new(middle),
             right: Arc::new(right),
+            cache: NodeCache::new(),
           });
           i += 3;
         } else if i + 1 < raw.len() {
@@ -866,6 +1159,7 @@ where
             size: left.len() + middle.len(),
             left: Arc::new(left),
             middle: Arc::new(middle),
+            cache: NodeCache::new(),
           });
           i += 2;
         } else {
@@ -878,36 +1172,9 @@ where
     raw[0].to_owned()
   }
 
-  pub fn check_structure(&self) -> Result<(), String> {
-    match self {
-      Leaf { .. } => Ok(()),
-      Branch2 { left, middle, size } => {
-        if *size != left.len() + middle.len() {
-          return Err(format!("Bad size at branch {}", self.format_inline()));
-        }
-
-        left.check_structure()?;
-        middle.check_structure()?;
-
-        Ok(())
-      }
-      Branch3 { left, middle, right, size } => {
-        if *size != left.len() + middle.len() + right.len() {
-          return Err(format!("Bad size at branch {}", self.format_inline()));
-        }
-
-        left.check_structure()?;
-        middle.check_structure()?;
-        right.check_structure()?;
-
-        Ok(())
-      }
-    }
-  }
-
   /// excludes value at end_idx, kept aligned with JS & Clojure
   /// does not check at inside
-  pub fn take_left(&self, end_idx: usize) -> Result<Self, String> {
+  pub fn take_left(&self, end_idx: usize) -> Result<Self, TernaryTreeError> {
     // echo "take_left {tree.formatListInline}: {start_idx}..{end_idx}"
 
     match self {
@@ -915,11 +1182,18 @@ where
         if end_idx == 1 {
           Ok(self.to_owned())
         } else {
-          Err(format!("Invalid take_left for a leaf: {end_idx}"))
+          Err(TernaryTreeError::InvalidRange { start: 0, end: end_idx })
+        }
+      }
+      Chunk(xs) => {
+        if end_idx > 0 && end_idx <= xs.len() {
+          Ok(Chunk(xs[..end_idx].to_vec()))
+        } else {
+          Err(TernaryTreeError::InvalidRange { start: 0, end: end_idx })
         }
       }
 
-      Branch2 { left, middle, size } => {
+      Branch2 { left, middle, size, .. } => {
         if end_idx == *size {
           Ok(self.to_owned())
         } else if end_idx <= left.len() {
@@ -932,10 +1206,13 @@ where
             size: left_len + middle_cut.len(),
             left: left.to_owned(),
             middle: Arc::new(middle_cut),
+            cache: NodeCache::new(),
           })
         }
       }
-      Branch3 { left, right, middle, size } => {
+      Branch3 {
+        left, right, middle, size, ..
+      } => {
         let base1 = left.len();
         let base2 = base1 + middle.len();
         if end_idx == *size {
@@ -949,6 +1226,7 @@ where
             size: left.len() + middle_cut.len(),
             left: left.to_owned(),
             middle: Arc::new(middle_cut),
+            cache: NodeCache::new(),
           })
         } else {
           let right_cut = right.take_left(end_idx - base2)?;
@@ -957,6 +1235,7 @@ where
             left: left.to_owned(),
             middle: middle.clone(),
             right: Arc::new(right_cut),
+            cache: NodeCache::new(),
           })
         }
       }
@@ -965,7 +1244,7 @@ where
 
   /// excludes value at end_idx, kept aligned with JS & Clojure
   /// does not check at inside
-  pub fn take_right(&self, start_idx: usize) -> Result<Self, String> {
+  pub fn take_right(&self, start_idx: usize) -> Result<Self, TernaryTreeError> {
     // println!("take right {}: {start_idx}", self.format_inline());
 
     match self {
@@ -973,7 +1252,17 @@ where
         if start_idx == 0 {
           Ok(self.to_owned())
         } else {
-          Err(format!("Invalid take_right range for a leaf: {start_idx}",))
+          Err(TernaryTreeError::InvalidRange { start: start_idx, end: 1 })
+        }
+      }
+      Chunk(xs) => {
+        if start_idx < xs.len() {
+          Ok(Chunk(xs[start_idx..].to_vec()))
+        } else {
+          Err(TernaryTreeError::InvalidRange {
+            start: start_idx,
+            end: xs.len(),
+          })
         }
       }
 
@@ -991,6 +1280,7 @@ where
             size: left_cut.len() + middle.len(),
             left: Arc::new(left_cut),
             middle: middle.to_owned(),
+            cache: NodeCache::new(),
           })
         }
       }
@@ -1008,6 +1298,7 @@ where
             size: middle_cut.len() + right.len(),
             left: Arc::new(middle_cut),
             middle: right.to_owned(),
+            cache: NodeCache::new(),
           })
         } else {
           let left_cut = left.take_right(start_idx)?;
@@ -1016,6 +1307,7 @@ where
             left: Arc::new(left_cut),
             middle: middle.clone(),
             right: right.to_owned(),
+            cache: NodeCache::new(),
           })
         }
       }
@@ -1024,7 +1316,7 @@ where
 
   /// excludes value at end_idx, kept aligned with JS & Clojure
   /// does not check at inside
-  pub fn slice(&self, start_idx: usize, end_idx: usize) -> Result<Self, String> {
+  pub fn slice(&self, start_idx: usize, end_idx: usize) -> Result<Self, TernaryTreeError> {
     // echo "slice {tree.formatListInline}: {start_idx}..{end_idx}"
 
     match self {
@@ -1032,11 +1324,24 @@ where
         if start_idx == 0 && end_idx == 1 {
           Ok(self.to_owned())
         } else {
-          Err(format!("Invalid slice range for a leaf: {start_idx} {end_idx}"))
+          Err(TernaryTreeError::InvalidRange {
+            start: start_idx,
+            end: end_idx,
+          })
+        }
+      }
+      Chunk(xs) => {
+        if start_idx < end_idx && end_idx <= xs.len() {
+          Ok(Chunk(xs[start_idx..end_idx].to_vec()))
+        } else {
+          Err(TernaryTreeError::InvalidRange {
+            start: start_idx,
+            end: end_idx,
+          })
         }
       }
 
-      Branch2 { left, middle, size } => {
+      Branch2 { left, middle, size, .. } => {
         let left_size = left.len();
         if start_idx == 0 && end_idx == *size {
           Ok(self.to_owned())
@@ -1054,6 +1359,7 @@ where
             size: left_len + middle_cut.len(),
             left: left.to_owned(),
             middle: Arc::new(middle_cut),
+            cache: NodeCache::new(),
           })
         } else if end_idx == *size {
           // take part of left and whole middle
@@ -1062,6 +1368,7 @@ where
             size: left_cut.len() + middle.len(),
             left: Arc::new(left_cut),
             middle: middle.to_owned(),
+            cache: NodeCache::new(),
           })
         } else {
           let left_cut = left.take_right(start_idx)?;
@@ -1071,10 +1378,13 @@ where
             size: left_cut.len() + middle_cut.len(),
             left: Arc::new(left_cut),
             middle: Arc::new(middle_cut),
+            cache: NodeCache::new(),
           })
         }
       }
-      Branch3 { left, right, middle, size } => {
+      Branch3 {
+        left, right, middle, size, ..
+      } => {
         let base1 = left.len();
         let base2 = base1 + middle.len();
         if start_idx == 0 && end_idx == *size {
@@ -1091,6 +1401,7 @@ where
               size: middle_cut.len() + right.len(),
               left: Arc::new(middle_cut),
               middle: right.to_owned(),
+              cache: NodeCache::new(),
             })
           } else {
             let middle_cut = middle.take_right(start_idx - base1)?;
@@ -1100,6 +1411,7 @@ where
               size: middle_cut.len() + right_cut.len(),
               left: Arc::new(middle_cut),
               middle: Arc::new(right_cut),
+              cache: NodeCache::new(),
             })
           }
         } else if end_idx <= base1 {
@@ -1112,6 +1424,7 @@ where
               size: left.len() + middle_cut.len(),
               left: left.to_owned(),
               middle: Arc::new(middle_cut),
+              cache: NodeCache::new(),
             })
           } else {
             let left_cut = left.take_right(start_idx)?;
@@ -1121,6 +1434,7 @@ where
               size: left_cut.len() + middle_cut.len(),
               left: Arc::new(left_cut),
               middle: Arc::new(middle_cut),
+              cache: NodeCache::new(),
             })
           }
         } else {
@@ -1131,6 +1445,7 @@ where
             left: Arc::new(left_cut),
             middle: middle.clone(),
             right: Arc::new(right_cut),
+            cache: NodeCache::new(),
           })
         }
       }
@@ -1143,7 +1458,13 @@ where
   pub fn split(&self, idx: usize) -> (Self, Self) {
     match self {
       Leaf(_value) => unreachable!("Invalid split index for a leaf: {}", idx),
-      Branch2 { left, middle, size } => {
+      Chunk(xs) => {
+        if idx == 0 || idx >= xs.len() {
+          unreachable!("Invalid split index for a chunk: {}", idx)
+        }
+        (Chunk(xs[..idx].to_vec()), Chunk(xs[idx..].to_vec()))
+      }
+      Branch2 { left, middle, size, .. } => {
         if idx == 0 {
           unreachable!("Invalid split index 0 for a branch2: {}", idx)
         } else if idx < left.len() {
@@ -1155,6 +1476,7 @@ where
               size: size - idx,
               left: Arc::new(cut_b),
               middle: middle.to_owned(),
+              cache: NodeCache::new(),
             },
           )
         } else if idx == left.len() {
@@ -1169,6 +1491,7 @@ where
               size: left.len() + cut_a.len(),
               left: left.to_owned(),
               middle: Arc::new(cut_a),
+              cache: NodeCache::new(),
             },
             cut_b,
           )
@@ -1176,7 +1499,9 @@ where
           unreachable!("Invalid split index end for a branch2: {}", idx)
         }
       }
-      Branch3 { left, middle, right, size } => {
+      Branch3 {
+        left, middle, right, size, ..
+      } => {
         if idx == 0 {
           unreachable!("Invalid split index 0 for a branch3: {}", idx)
         } else if idx < left.len() {
@@ -1189,6 +1514,7 @@ where
               left: Arc::new(cut_b),
               middle: middle.to_owned(),
               right: right.to_owned(),
+              cache: NodeCache::new(),
             },
           )
         } else if idx == left.len() {
@@ -1198,6 +1524,7 @@ where
               size: middle.len() + right.len(),
               left: middle.to_owned(),
               middle: right.to_owned(),
+              cache: NodeCache::new(),
             },
           )
         } else if idx - left.len() < middle.len() {
@@ -1207,11 +1534,13 @@ where
               size: left.len() + cut_a.len(),
               left: left.to_owned(),
               middle: Arc::new(cut_a),
+              cache: NodeCache::new(),
             },
             Branch2 {
               size: cut_b.len() + right.len(),
               left: Arc::new(cut_b),
               middle: right.to_owned(),
+              cache: NodeCache::new(),
             },
           )
         } else if idx == left.len() + middle.len() {
@@ -1220,6 +1549,7 @@ where
               size: left.len() + middle.len(),
               left: left.to_owned(),
               middle: middle.to_owned(),
+              cache: NodeCache::new(),
             },
             (**right).to_owned(),
           )
@@ -1231,6 +1561,7 @@ where
               size: left.len() + middle.len(),
               left: left.to_owned(),
               middle: middle.to_owned(),
+              cache: NodeCache::new(),
             },
             (**right).to_owned(),
           )
@@ -1243,6 +1574,7 @@ where
               left: left.to_owned(),
               middle: middle.to_owned(),
               right: Arc::new(cut_a),
+              cache: NodeCache::new(),
             },
             cut_b,
           )
@@ -1254,33 +1586,22 @@ where
   pub fn reverse(&self) -> Self {
     match self {
       Leaf { .. } => self.to_owned(),
-      Branch2 { left, middle, size } => Branch2 {
+      Chunk(xs) => Chunk(xs.iter().rev().cloned().collect()),
+      Branch2 { left, middle, size, .. } => Branch2 {
         size: *size,
         left: Arc::new(middle.reverse()),
         middle: Arc::new(left.reverse()),
+        cache: NodeCache::new(),
       },
-      Branch3 { left, middle, right, size } => Branch3 {
+      Branch3 {
+        left, middle, right, size, ..
+      } => Branch3 {
         size: *size,
 
         left: Arc::new(right.reverse()),
         middle: Arc::new(middle.reverse()),
         right: Arc::new(left.reverse()),
-      },
-    }
-  }
-  pub fn map<V>(&self, f: Arc<dyn Fn(&T) -> V>) -> TernaryTree<V> {
-    match self {
-      Leaf(value) => Leaf(f(value)),
-      Branch2 { left, middle, size } => Branch2 {
-        size: *size,
-        left: Arc::new(left.map(f.clone())),
-        middle: Arc::new(middle.map(f.clone())),
-      },
-      Branch3 { left, middle, right, size } => Branch3 {
-        size: *size,
-        left: Arc::new(left.map(f.clone())),
-        middle: Arc::new(middle.map(f.clone())),
-        right: Arc::new(right.map(f.clone())),
+        cache: NodeCache::new(),
       },
     }
   }
@@ -1292,51 +1613,101 @@ where
     }
     xs
   }
+}
 
-  pub fn traverse(&self, f: &mut dyn FnMut(&T)) {
+impl<T: Display> TernaryTree<T> {
+  /// turn into a representation in triples, with `_` for holes
+  pub fn format_inline(&self) -> String {
     match self {
-      Leaf(value) => f(value),
+      Leaf(value) => value.to_string(),
+      Chunk(xs) => format!("[{}]", xs.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(" ")),
       Branch2 { left, middle, .. } => {
-        left.traverse(f);
-        middle.traverse(f);
+        // TODO maybe need more informations here
+        format!("({} {})", left.format_inline(), middle.format_inline())
       }
       Branch3 { left, middle, right, .. } => {
-        left.traverse(f);
-        middle.traverse(f);
-        right.traverse(f);
+        // TODO maybe need more informations here
+        format!("({} {} {})", left.format_inline(), middle.format_inline(), right.format_inline())
       }
     }
   }
+}
 
-  pub fn traverse_result<S>(&self, f: &mut dyn FnMut(&T) -> Result<(), S>) -> Result<(), S> {
+impl<T: PartialEq> TernaryTree<T> {
+  pub fn index_of(&self, item: &T) -> Option<usize> {
     match self {
-      Leaf(value) => f(value),
-      Branch2 { left, middle, .. } => {
-        left.traverse_result(f)?;
-        middle.traverse_result(f)?;
-        Ok(())
+      Leaf(value) => {
+        if item == value {
+          Some(0)
+        } else {
+          None
+        }
       }
-      Branch3 { left, middle, right, .. } => {
-        left.traverse_result(f)?;
-        middle.traverse_result(f)?;
-        right.traverse_result(f)?;
-        Ok(())
+      Chunk(xs) => xs.iter().position(|x| x == item),
+      Branch2 { left, middle, .. } => left.index_of(item).or_else(|| middle.index_of(item).map(|pos| pos + left.len())),
+      Branch3 { left, middle, right, .. } => left
+        .index_of(item)
+        .or_else(|| middle.index_of(item).map(|pos| pos + left.len()))
+        .or_else(|| right.index_of(item).map(|pos| pos + left.len() + middle.len())),
+    }
+  }
+
+  // index from end, returns 0 when item found at end of original list
+  pub fn last_index_of(&self, item: &T) -> Option<usize> {
+    match self {
+      Leaf(value) => {
+        if item == value {
+          Some(0)
+        } else {
+          None
+        }
       }
+      Chunk(xs) => xs.iter().rev().position(|x| x == item),
+      Branch2 { left, middle, .. } => middle
+        .last_index_of(item)
+        .or_else(|| left.last_index_of(item).map(|pos| pos + middle.len())),
+      Branch3 { left, middle, right, .. } => right
+        .last_index_of(item)
+        .or_else(|| middle.last_index_of(item).map(|pos| pos + right.len()))
+        .or_else(|| left.last_index_of(item).map(|pos| pos + middle.len() + right.len())),
     }
   }
 
-  pub fn iter(&self) -> TernaryTreeIterator<T> {
-    TernaryTreeIterator {
-      value: self,
-      index: 0,
-      size: self.len(),
+  /// recursively check structure
+  pub fn eq_shape(&self, ys: &Self) -> bool {
+    if self.len() != ys.len() {
+      return false;
+    }
+
+    match (self, ys) {
+      (Leaf(value), Leaf(v2)) => value == v2,
+      (Chunk(xs), Chunk(ys)) => xs == ys,
+      (
+        Branch2 { left, middle, .. },
+        Branch2 {
+          left: left2,
+          middle: middle2,
+          ..
+        },
+      ) => left.eq_shape(left2) && middle.eq_shape(middle2),
+      (
+        Branch3 { left, middle, right, .. },
+        Branch3 {
+          left: left2,
+          middle: middle2,
+          right: right2,
+          ..
+        },
+      ) => left.eq_shape(left2) && middle.eq_shape(middle2) && right.eq_shape(right2),
+
+      (_, _) => false,
     }
   }
 }
 
 impl<T> Display for TernaryTree<T>
 where
-  T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash,
+  T: Display,
 {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     write!(f, "(TernaryTree")?;
@@ -1347,97 +1718,60 @@ where
   }
 }
 
-// experimental code to turn `&TernaryTree<_>` into iterator
-impl<'a, T> IntoIterator for &'a TernaryTree<T>
-where
-  T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash,
-{
-  type Item = &'a T;
-  type IntoIter = TernaryTreeIterator<'a, T>;
-
-  fn into_iter(self) -> Self::IntoIter {
-    TernaryTreeIterator {
-      value: self,
-      index: 0,
-      size: self.len(),
-    }
-  }
-}
-
-pub struct TernaryTreeIterator<'a, T> {
-  value: &'a TernaryTree<T>,
-  index: usize,
-  size: usize,
-}
-
-impl<'a, T> Iterator for TernaryTreeIterator<'a, T>
-where
-  T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash,
-{
-  type Item = &'a T;
-  fn next(&mut self) -> Option<Self::Item> {
-    if self.index < self.size {
-      // println!("get: {} {}", self.value.format_inline(), self.index);
-      let ret = self.value.loop_get(self.index);
-      self.index += 1;
-      Some(ret)
-    } else {
-      None
-    }
+impl<T: Eq> TernaryTree<T> {
+  /// same result as `==`, but subtrees shared by both trees are skipped by pointer,
+  /// so comparing two versions that differ in a few places only visits the changed paths
+  pub fn eq_shared(&self, ys: &Self) -> bool {
+    self.len() == ys.len() && eq_same_size(self, ys)
   }
 }
 
-impl<T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash> PartialEq for TernaryTree<T> {
+/// compared element by element, even for subtrees shared by both trees,
+/// since values like `NaN` are not equal to themselves. use `eq_shared` when `T: Eq`
+impl<T: PartialEq> PartialEq for TernaryTree<T> {
   fn eq(&self, ys: &Self) -> bool {
     if self.len() != ys.len() {
       return false;
     }
 
-    for idx in 0..ys.len() {
-      if self.loop_get(idx) != ys.loop_get(idx) {
-        return false;
-      }
-    }
-
-    true
+    self.iter().eq(ys.iter())
   }
 }
 
-impl<T> Eq for TernaryTree<T> where T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash {}
+impl<T> Eq for TernaryTree<T> where T: Eq {}
 
+/// by default shorter tree comes first, and trees of same size are compared element by element.
+/// with feature `lexicographic-ord`, trees are compared element by element like slices.
+/// shared subtrees are not skipped here for the same reason as `PartialEq`, `Ord` skips them
 impl<T> PartialOrd for TernaryTree<T>
 where
-  T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash,
+  T: PartialOrd,
 {
   fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-    Some(self.cmp(other))
+    if self.len() == other.len() || cfg!(feature = "lexicographic-ord") {
+      self.iter().partial_cmp(other.iter())
+    } else {
+      self.len().partial_cmp(&other.len())
+    }
   }
 }
 
 impl<T> Ord for TernaryTree<T>
 where
-  T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash,
+  T: Ord,
 {
   fn cmp(&self, other: &Self) -> Ordering {
     if self.len() == other.len() {
-      for idx in 0..self.len() {
-        match self.loop_get(idx).cmp(other.loop_get(idx)) {
-          Ordering::Equal => {}
-          a => return a,
-        }
-      }
-
-      Ordering::Equal
+      cmp_same_size(self, other)
+    } else if cfg!(feature = "lexicographic-ord") {
+      self.iter().cmp(other.iter())
     } else {
       self.len().cmp(&other.len())
     }
   }
 }
 
-impl<T> Index<usize> for TernaryTree<T>
-where
-  T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash,
-{
+impl<T> Index<usize> for TernaryTree<T> {
   type Output = T;
 
   fn index<'b>(&self, idx: usize) -> &Self::Output {
@@ -1448,13 +1782,18 @@ where
 
 impl<T> Hash for TernaryTree<T>
 where
-  T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash,
+  T: Hash,
 {
   fn hash<H: Hasher>(&self, state: &mut H) {
     match self {
       Leaf(value) => {
         value.hash(state);
       }
+      Chunk(xs) => {
+        for x in xs {
+          x.hash(state);
+        }
+      }
       Branch2 { left, middle, .. } => {
         left.hash(state);
         middle.hash(state);
@@ -1471,10 +1810,10 @@ where
 /// internal function for mutable writing
 fn write_leaves<T>(xs: &TernaryTree<T>, acc: &mut Vec<TernaryTree<T>>, counter: &Cell<usize>)
 where
-  T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash,
+  T: Clone,
 {
   match xs {
-    Leaf { .. } => {
+    Leaf { .. } | Chunk(_) => {
       let idx = counter.take();
       acc.push(xs.to_owned());
 
@@ -1491,3 +1830,157 @@ where
     }
   }
 }
+
+/// insert into a chunk before or after `idx`, a full chunk is split into two halves
+fn chunk_insert<T: Clone>(xs: &[T], idx: usize, item: T, after: bool) -> Result<TernaryTree<T>, TernaryTreeError> {
+  if idx >= xs.len() {
+    return Err(TernaryTreeError::IndexOutOfBounds { index: idx, len: xs.len() });
+  }
+  let pos = if after { idx + 1 } else { idx };
+  let mut ys = Vec::with_capacity(xs.len() + 1);
+  ys.extend_from_slice(&xs[..pos]);
+  ys.push(item);
+  ys.extend_from_slice(&xs[pos..]);
+  if ys.len() <= CHUNK_SIZE {
+    Ok(Chunk(ys))
+  } else {
+    let right = ys.split_off(ys.len() / 2);
+    let branch = Branch2 {
+      size: ys.len() + right.len(),
+      left: Arc::new(Chunk(ys)),
+      middle: Arc::new(Chunk(right)),
+      cache: NodeCache::new(),
+    };
+    Ok(branch)
+  }
+}
+
+/// builds for `rebuild_list`, nodes are shared into the new tree instead of being cloned
+fn rebuild_nodes<T>(xs: &[Arc<TernaryTree<T>>], factor: u8) -> Arc<TernaryTree<T>> {
+  let size = xs.len();
+  match size {
+    0 => unreachable!("Does not work for empty list"),
+    1..=3 => rebuild_nodes_side(xs),
+    _ => {
+      let side_capacity = triple_size(factor - 1);
+      if side_capacity * 2 < size {
+        let divided = (side_capacity, size - side_capacity - side_capacity, side_capacity);
+
+        let left = rebuild_nodes_side(&xs[..divided.0]);
+        let middle = rebuild_nodes(&xs[divided.0..divided.0 + divided.1], factor + 1);
+        let right = rebuild_nodes_side(&xs[divided.0 + divided.1..]);
+
+        Arc::new(Branch3 {
+          size: left.len() + middle.len() + right.len(),
+          left,
+          middle,
+          right,
+          cache: NodeCache::new(),
+        })
+      } else {
+        rebuild_nodes_side(xs)
+      }
+    }
+  }
+}
+
+/// builds for `rebuild_list_side`
+fn rebuild_nodes_side<T>(xs: &[Arc<TernaryTree<T>>]) -> Arc<TernaryTree<T>> {
+  match xs.len() {
+    0 => unreachable!("Does not work for empty list"),
+    1 => xs[0].to_owned(),
+    2 => Arc::new(Branch2 {
+      size: xs[0].len() + xs[1].len(),
+      left: xs[0].to_owned(),
+      middle: xs[1].to_owned(),
+      cache: NodeCache::new(),
+    }),
+    3 => Arc::new(Branch3 {
+      size: xs[0].len() + xs[1].len() + xs[2].len(),
+      left: xs[0].to_owned(),
+      middle: xs[1].to_owned(),
+      right: xs[2].to_owned(),
+      cache: NodeCache::new(),
+    }),
+    size => {
+      let divided = divide_ternary_sizes(size);
+
+      let left = rebuild_nodes_side(&xs[..divided.0]);
+      let middle = rebuild_nodes_side(&xs[divided.0..divided.0 + divided.1]);
+      let right = rebuild_nodes_side(&xs[divided.0 + divided.1..]);
+
+      Arc::new(Branch3 {
+        size: left.len() + middle.len() + right.len(),
+        left,
+        middle,
+        right,
+        cache: NodeCache::new(),
+      })
+    }
+  }
+}
+
+/// compare trees of same size, subtrees shared by both sides are skipped by pointer,
+/// falls back to comparing elements where shapes are different.
+/// only used for `Ord`, since values like `NaN` in `PartialOrd` are not equal to themselves
+fn cmp_same_size<T: Ord>(xs: &TernaryTree<T>, ys: &TernaryTree<T>) -> Ordering {
+  match (xs, ys) {
+    (Leaf(a), Leaf(b)) => a.cmp(b),
+    (
+      Branch2 { left, middle, .. },
+      Branch2 {
+        left: left2,
+        middle: middle2,
+        ..
+      },
+    ) if left.len() == left2.len() => cmp_child(left, left2).then_with(|| cmp_child(middle, middle2)),
+    (
+      Branch3 { left, middle, right, .. },
+      Branch3 {
+        left: left2,
+        middle: middle2,
+        right: right2,
+        ..
+      },
+    ) if left.len() == left2.len() && middle.len() == middle2.len() => cmp_child(left, left2)
+      .then_with(|| cmp_child(middle, middle2))
+      .then_with(|| cmp_child(right, right2)),
+    (_, _) => xs.iter().cmp(ys.iter()),
+  }
+}
+
+fn cmp_child<T: Ord>(x: &Arc<TernaryTree<T>>, y: &Arc<TernaryTree<T>>) -> Ordering {
+  if Arc::ptr_eq(x, y) { Ordering::Equal } else { cmp_same_size(x, y) }
+}
+
+/// like `cmp_same_size`, for `eq_shared`
+fn eq_same_size<T: Eq>(xs: &TernaryTree<T>, ys: &TernaryTree<T>) -> bool {
+  match (xs, ys) {
+    (Leaf(a), Leaf(b)) => a == b,
+    (Chunk(a), Chunk(b)) => a == b,
+    (
+      Branch2 { left, middle, .. },
+      Branch2 {
+        left: left2,
+        middle: middle2,
+        ..
+      },
+    ) if left.len() == left2.len() => eq_child(left, left2) && eq_child(middle, middle2),
+    (
+      Branch3 { left, middle, right, .. },
+      Branch3 {
+        left: left2,
+        middle: middle2,
+        right: right2,
+        ..
+      },
+    ) if left.len() == left2.len() && middle.len() == middle2.len() => {
+      eq_child(left, left2) && eq_child(middle, middle2) && eq_child(right, right2)
+    }
+    (_, _) => xs.iter().eq(ys.iter()),
+  }
+}
+
+fn eq_child<T: Eq>(x: &Arc<TernaryTree<T>>, y: &Arc<TernaryTree<T>>) -> bool {
+  Arc::ptr_eq(x, y) || eq_same_size(x, y)
+}
@@ -0,0 +1,147 @@
+//! collects a `StructureReport` by walking through the whole tree.
+
+use super::CHUNK_SIZE;
+use super::TernaryTree::{self, *};
+
+use crate::report::{StructureIssue, StructureReport};
+use crate::util::{depth_limit, triple_size};
+
+impl<T> TernaryTree<T> {
+  pub fn structure_report(&self) -> StructureReport {
+    let mut report = StructureReport {
+      len: self.len(),
+      depth_limit: depth_limit(self.len()),
+      ..StructureReport::default()
+    };
+    self.walk_report(0, 0, &mut report);
+    report.balanced_depth = balanced_depth(report.units);
+    report.left_edge_depth = self.edge_depth(|t| t.first_child());
+    report.right_edge_depth = self.edge_depth(|t| t.last_child());
+    if report.max_depth > report.depth_limit {
+      report.issues.push(StructureIssue::TooDeep {
+        depth: report.max_depth,
+        limit: report.depth_limit,
+      });
+    }
+    let ends = [
+      (0, report.left_edge_depth),
+      (report.len - self.last_leaf().len(), report.right_edge_depth),
+    ];
+    for (offset, depth) in ends {
+      if depth > report.depth_limit {
+        report.issues.push(StructureIssue::FingerTooDeep {
+          offset,
+          depth,
+          limit: report.depth_limit,
+        });
+      }
+    }
+    report.finger_fill = self.finger_fill(2);
+    report
+  }
+
+  fn walk_report(&self, offset: usize, depth: usize, report: &mut StructureReport) {
+    match self {
+      Leaf(_) => {
+        report.units += 1;
+        report.max_depth = report.max_depth.max(depth);
+      }
+      Chunk(xs) => {
+        report.units += 1;
+        report.max_depth = report.max_depth.max(depth);
+        if xs.is_empty() || xs.len() > CHUNK_SIZE {
+          report.issues.push(StructureIssue::InvalidChunk {
+            offset,
+            depth,
+            size: xs.len(),
+          });
+        }
+      }
+      Branch2 { size, left, middle, .. } => {
+        let actual = left.len() + middle.len();
+        if *size != actual {
+          report.issues.push(StructureIssue::SizeMismatch {
+            offset,
+            depth,
+            expected: *size,
+            actual,
+          });
+        }
+        left.walk_report(offset, depth + 1, report);
+        middle.walk_report(offset + left.len(), depth + 1, report);
+      }
+      Branch3 {
+        size, left, middle, right, ..
+      } => {
+        let actual = left.len() + middle.len() + right.len();
+        if *size != actual {
+          report.issues.push(StructureIssue::SizeMismatch {
+            offset,
+            depth,
+            expected: *size,
+            actual,
+          });
+        }
+        left.walk_report(offset, depth + 1, report);
+        middle.walk_report(offset + left.len(), depth + 1, report);
+        right.walk_report(offset + left.len() + middle.len(), depth + 1, report);
+      }
+    }
+  }
+
+  fn first_child(&self) -> Option<&Self> {
+    match self {
+      Branch2 { left, .. } | Branch3 { left, .. } => Some(left),
+      _ => None,
+    }
+  }
+
+  fn last_child(&self) -> Option<&Self> {
+    match self {
+      Branch2 { middle, .. } => Some(middle),
+      Branch3 { right, .. } => Some(right),
+      _ => None,
+    }
+  }
+
+  fn edge_depth(&self, child: fn(&Self) -> Option<&Self>) -> usize {
+    let mut depth = 0;
+    let mut node = self;
+    while let Some(next) = child(node) {
+      depth += 1;
+      node = next;
+    }
+    depth
+  }
+
+  /// follows branches in the middle like `push_right_main` and `push_left_main` do,
+  /// each side is expected to hold no more than `3^(n-1)` units, returns the largest fill in percent
+  fn finger_fill(&self, n: u8) -> usize {
+    if let Branch3 { left, middle, right, .. } = self {
+      let left_unit = if matches!(self.first_leaf(), Chunk(_)) { CHUNK_SIZE } else { 1 };
+      let right_unit = if matches!(self.last_leaf(), Chunk(_)) { CHUNK_SIZE } else { 1 };
+      if self.len() <= triple_size(n) * left_unit.min(right_unit) {
+        return 0;
+      }
+      let fill = [(left, left_unit), (right, right_unit)]
+        .iter()
+        .map(|(side, unit)| side.len() * 100 / (triple_size(n - 1) * unit))
+        .max()
+        .unwrap_or(0);
+      fill.max(middle.finger_fill(n + 1))
+    } else {
+      0
+    }
+  }
+}
+
+/// smallest depth to hold this many leaves in a ternary tree
+fn balanced_depth(units: usize) -> usize {
+  let mut depth = 0;
+  let mut capacity = 1;
+  while capacity < units {
+    capacity *= 3;
+    depth += 1;
+  }
+  depth
+}
@@ -0,0 +1,100 @@
+//! Graphviz DOT output of trees. nodes are identified by `Arc` pointers,
+//! so a node shared by several versions of a list is drawn once, and filled with gray.
+
+use super::TernaryTree::{self, *};
+
+use std::collections::HashMap;
+use std::fmt::{Display, Write};
+use std::sync::Arc;
+
+/// collects nodes and edges, ids are given in the order nodes are reached
+struct DotGraph<T> {
+  ids: HashMap<*const TernaryTree<T>, usize>,
+  /// label and number of edges pointing to each node
+  nodes: Vec<(String, usize)>,
+  edges: Vec<(&'static str, usize, usize)>,
+}
+
+impl<T: Display> DotGraph<T> {
+  fn add_node(&mut self, tree: &TernaryTree<T>) -> usize {
+    let id = self.nodes.len();
+    self.nodes.push((node_label(tree), 0));
+    let children: &[&Arc<TernaryTree<T>>] = match tree {
+      Leaf(_) | Chunk(_) => &[],
+      Branch2 { left, middle, .. } => &[left, middle],
+      Branch3 { left, middle, right, .. } => &[left, middle, right],
+    };
+    for (child, name) in children.iter().zip(["left", "middle", "right"]) {
+      let child_id = self.add_child(child);
+      self.edges.push((name, id, child_id));
+    }
+    id
+  }
+
+  fn add_child(&mut self, child: &Arc<TernaryTree<T>>) -> usize {
+    let id = match self.ids.get(&Arc::as_ptr(child)) {
+      Some(id) => *id,
+      None => {
+        let id = self.add_node(child);
+        self.ids.insert(Arc::as_ptr(child), id);
+        id
+      }
+    };
+    self.nodes[id].1 += 1;
+    id
+  }
+}
+
+fn node_label<T: Display>(tree: &TernaryTree<T>) -> String {
+  match tree {
+    Leaf(value) => value.to_string(),
+    Chunk(xs) => format!("[{}]", xs.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(" ")),
+    Branch2 { size, .. } => format!("Branch2 ({size})"),
+    Branch3 { size, .. } => format!("Branch3 ({size})"),
+  }
+}
+
+/// quotes for DOT strings
+fn escape(label: &str) -> String {
+  label.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+impl<T: Display> TernaryTree<T> {
+  /// one graph for all versions, `None` for an empty list
+  pub(crate) fn versions_to_dot<'a>(versions: impl Iterator<Item = Option<&'a Self>>) -> String
+  where
+    T: 'a,
+  {
+    let mut graph = DotGraph {
+      ids: HashMap::new(),
+      nodes: vec![],
+      edges: vec![],
+    };
+    let mut roots = vec![];
+    for version in versions {
+      roots.push(version.map(|tree| graph.add_node(tree)));
+    }
+
+    let mut out = String::from("digraph TernaryTree {\n  node [shape=box];\n");
+    for (idx, root) in roots.iter().enumerate() {
+      match root {
+        Some(id) => {
+          let _ = writeln!(out, "  v{idx} [label=\"v{idx}\", shape=plaintext];");
+          let _ = writeln!(out, "  v{idx} -> n{id};");
+        }
+        None => {
+          let _ = writeln!(out, "  v{idx} [label=\"v{idx} (empty)\", shape=plaintext];");
+        }
+      }
+    }
+    for (id, (label, refs)) in graph.nodes.iter().enumerate() {
+      let style = if *refs > 1 { ", style=filled, fillcolor=lightgray" } else { "" };
+      let _ = writeln!(out, "  n{id} [label=\"{}\"{style}];", escape(label));
+    }
+    for (name, from, to) in &graph.edges {
+      let _ = writeln!(out, "  n{from} -> n{to} [label=\"{name}\"];");
+    }
+    out.push_str("}\n");
+    out
+  }
+}
@@ -12,44 +12,44 @@
 //! Tree layout from 0 to 159 watch [video](https://www.bilibili.com/video/BV1F34y147V7) or try [live demo](https://github.com/calcit-lang/explain-ternary-tree).
 
 use super::TernaryTree::{self, *};
+use super::{CHUNK_SIZE, NodeCache};
 
-use std::fmt::{Debug, Display};
-use std::hash::Hash;
 use std::sync::Arc;
 
 use crate::util::triple_size;
 
-impl<T> TernaryTree<T>
-where
-  T: Clone + Display + Eq + PartialEq + Debug + Ord + PartialOrd + Hash,
-{
-  // for main branches detect keep a finger-tree like shallow-deep-shallow shape
-  fn push_right_main(&self, item: Self, n: u8) -> Self {
+impl<T: Clone> TernaryTree<T> {
+  // for main branches detect keep a finger-tree like shallow-deep-shallow shape.
+  // `unit` is the size of a full leaf, a new chunk is placed as if it's already full since it's filled later
+  fn push_right_main(&self, item: Self, n: u8, unit: usize) -> Self {
+    let weight = item.len().max(unit);
     // println!("  iter: {} {:?}", self.format_inline(), mark);
 
-    if self.len() + item.len() <= triple_size(n) {
-      self.push_right_side(item)
+    if self.len() + weight <= triple_size(n) * unit {
+      self.push_right_side(item, unit)
     } else {
       match self {
-        Leaf(_) => self.push_right_side(item),
+        Leaf(_) | Chunk(_) => self.push_right_side(item, unit),
         Branch2 { size, left, middle, .. } => {
-          if middle.len() + item.len() > triple_size(n) {
+          if middle.len() + weight > triple_size(n) * unit {
             Branch3 {
               size: size + item.len(),
               left: left.to_owned(),
               middle: middle.to_owned(),
               right: Arc::new(item),
+              cache: NodeCache::new(),
             }
           } else {
             // pile items in the compact way like in sides
             // println!("    try n: {}", n);
             let item_size = item.len();
-            let changed_branch = middle.push_right_side(item);
+            let changed_branch = middle.push_right_side(item, unit);
 
             Branch2 {
               size: size + item_size,
               left: left.to_owned(),
               middle: Arc::new(changed_branch),
+              cache: NodeCache::new(),
             }
           }
         }
@@ -57,22 +57,24 @@ where
           size, left, middle, right, ..
         } => {
           // println!("    b3 n: {}", n);
-          if right.len() + item.len() > triple_size(n - 1) {
-            let changed_branch = middle.push_right_main((**right).to_owned(), n + 1);
+          if right.len() + weight > triple_size(n - 1) * unit {
+            let changed_branch = middle.push_right_main((**right).to_owned(), n + 1, unit);
             Branch3 {
               size: left.len() + changed_branch.len() + item.len(),
               left: left.to_owned(),
               middle: Arc::new(changed_branch),
               right: Arc::new(item),
+              cache: NodeCache::new(),
             }
           } else {
             let item_size = item.len();
-            let changed_branch = right.push_right_side(item);
+            let changed_branch = right.push_right_side(item, unit);
             Branch3 {
               size: size + item_size,
               left: left.to_owned(),
               middle: middle.to_owned(),
               right: Arc::new(changed_branch),
+              cache: NodeCache::new(),
             }
           }
         }
@@ -81,32 +83,35 @@ where
   }
 
   // for main branches detect keep a finger-tree like shallow-deep-shallow shape
-  fn push_left_main(&self, item: Self, n: u8) -> Self {
+  fn push_left_main(&self, item: Self, n: u8, unit: usize) -> Self {
+    let weight = item.len().max(unit);
     // println!("  iter: {} {:?}", self.format_inline(), mark);
 
-    if self.len() + item.len() <= triple_size(n) {
-      self.push_left_side(item)
+    if self.len() + weight <= triple_size(n) * unit {
+      self.push_left_side(item, unit)
     } else {
       match self {
-        Leaf(_) => self.push_left_side(item),
+        Leaf(_) | Chunk(_) => self.push_left_side(item, unit),
         Branch2 { size, left, middle, .. } => {
-          if left.len() + item.len() > triple_size(n) {
+          if left.len() + weight > triple_size(n) * unit {
             Branch3 {
               size: size + item.len(),
               left: Arc::new(item),
               middle: left.to_owned(),
               right: middle.to_owned(),
+              cache: NodeCache::new(),
             }
           } else {
             // pile items in the compact way like in sides
             // println!("    try n: {}", n);
             let item_size = item.len();
-            let changed_branch = left.push_left_side(item);
+            let changed_branch = left.push_left_side(item, unit);
 
             Branch2 {
               size: size + item_size,
               left: Arc::new(changed_branch),
               middle: middle.to_owned(),
+              cache: NodeCache::new(),
             }
           }
         }
@@ -114,22 +119,24 @@ where
           size, right, middle, left, ..
         } => {
           // println!("    b3 n: {}", n);
-          if left.len() + item.len() > triple_size(n - 1) {
-            let changed_branch = middle.push_left_main((**left).to_owned(), n + 1);
+          if left.len() + weight > triple_size(n - 1) * unit {
+            let changed_branch = middle.push_left_main((**left).to_owned(), n + 1, unit);
             Branch3 {
               size: right.len() + changed_branch.len() + item.len(),
               left: Arc::new(item),
               middle: Arc::new(changed_branch),
               right: right.to_owned(),
+              cache: NodeCache::new(),
             }
           } else {
             let item_size = item.len();
-            let changed_branch = left.push_left_side(item);
+            let changed_branch = left.push_left_side(item, unit);
             Branch3 {
               size: size + item_size,
               left: Arc::new(changed_branch),
               middle: middle.to_owned(),
               right: right.to_owned(),
+              cache: NodeCache::new(),
             }
           }
         }
@@ -138,47 +145,53 @@ where
   }
 
   // just pile items in the compact way
-  fn push_right_side(&self, item: Self) -> Self {
+  fn push_right_side(&self, item: Self, unit: usize) -> Self {
+    let weight = item.len().max(unit);
     // println!("  iter: {} {:?}", self.format_inline(), mark);
     match self {
-      Leaf(a) => Branch2 {
-        size: 1 + item.len(),
-        left: Arc::new(Leaf(a.to_owned())),
+      Leaf(_) | Chunk(_) => Branch2 {
+        size: self.len() + item.len(),
+        left: Arc::new(self.to_owned()),
         middle: Arc::new(item),
+        cache: NodeCache::new(),
       },
       Branch2 { size, left, middle, .. } => {
-        if middle.len() + item.len() > left.len() {
+        if middle.len() + weight > left.len() {
           Branch3 {
             size: size + item.len(),
             left: left.to_owned(),
             middle: middle.to_owned(),
             right: Arc::new(item),
+            cache: NodeCache::new(),
           }
         } else {
-          let changed_branch = middle.push_right_side(item.to_owned());
+          let changed_branch = middle.push_right_side(item.to_owned(), unit);
           Branch2 {
             size: size + item.len(),
             left: left.to_owned(),
             middle: Arc::new(changed_branch),
+            cache: NodeCache::new(),
           }
         }
       }
       Branch3 {
         size, left, middle, right, ..
       } => {
-        if right.len() + item.len() > middle.len() {
+        if right.len() + weight > middle.len() {
           Branch2 {
             size: size + item.len(),
             left: Arc::new(self.to_owned()),
             middle: Arc::new(item),
+            cache: NodeCache::new(),
           }
         } else {
-          let changed_branch = right.push_right_side(item.to_owned());
+          let changed_branch = right.push_right_side(item.to_owned(), unit);
           Branch3 {
             size: size + item.len(),
             left: left.to_owned(),
             middle: middle.to_owned(),
             right: Arc::new(changed_branch),
+            cache: NodeCache::new(),
           }
         }
       }
@@ -186,61 +199,134 @@ where
   }
 
   // just pile items in the compact way
-  fn push_left_side(&self, item: Self) -> Self {
+  fn push_left_side(&self, item: Self, unit: usize) -> Self {
+    let weight = item.len().max(unit);
     // println!("  iter: {} {:?}", self.format_inline(), mark);
     match self {
-      Leaf(a) => Branch2 {
-        size: 1 + item.len(),
+      Leaf(_) | Chunk(_) => Branch2 {
+        size: self.len() + item.len(),
         left: Arc::new(item),
-        middle: Arc::new(Leaf(a.to_owned())),
+        middle: Arc::new(self.to_owned()),
+        cache: NodeCache::new(),
       },
       Branch2 { size, left, middle, .. } => {
-        if left.len() + item.len() > middle.len() {
+        if left.len() + weight > middle.len() {
           Branch3 {
             size: size + item.len(),
             left: Arc::new(item),
             middle: left.to_owned(),
             right: middle.to_owned(),
+            cache: NodeCache::new(),
           }
         } else {
-          let changed_branch = left.push_left_side(item.to_owned());
+          let changed_branch = left.push_left_side(item.to_owned(), unit);
           Branch2 {
             size: size + item.len(),
             left: Arc::new(changed_branch),
             middle: middle.to_owned(),
+            cache: NodeCache::new(),
           }
         }
       }
       Branch3 {
         size, right, middle, left, ..
       } => {
-        if left.len() + item.len() > middle.len() {
+        if left.len() + weight > middle.len() {
           Branch2 {
             size: size + item.len(),
             left: Arc::new(item),
             middle: Arc::new(self.to_owned()),
+            cache: NodeCache::new(),
           }
         } else {
-          let changed_branch = left.push_left_side(item.to_owned());
+          let changed_branch = left.push_left_side(item.to_owned(), unit);
           Branch3 {
             size: size + item.len(),
             left: Arc::new(changed_branch),
             middle: middle.to_owned(),
             right: right.to_owned(),
+            cache: NodeCache::new(),
           }
         }
       }
     }
   }
 
+  /// appends into the last chunk when there's room. if the last chunk is full, a new chunk is started
   pub fn push_right(&self, item: T) -> Self {
-    // start with 2 so its left child branch has capability of only 3^1
-    self.push_right_main(Leaf(item), 2)
+    match self.last_leaf() {
+      Chunk(xs) if xs.len() < CHUNK_SIZE => self.push_right_chunk(std::iter::once(item)),
+      // start with 2 so its left child branch has capability of only 3^1
+      Chunk(_) => self.push_right_main(Chunk(vec![item]), 2, CHUNK_SIZE),
+      _ => self.push_right_main(Leaf(item), 2, 1),
+    }
   }
 
+  /// prepends into the first chunk when there's room. if the first chunk is full, a new chunk is started
   pub fn push_left(&self, item: T) -> Self {
-    // start with 2 so its left child branch has capability of only 3^1
-    self.push_left_main(Leaf(item), 2)
+    match self.first_leaf() {
+      Chunk(xs) if xs.len() < CHUNK_SIZE => self.push_left_chunk(item),
+      // start with 2 so its left child branch has capability of only 3^1
+      Chunk(_) => self.push_left_main(Chunk(vec![item]), 2, CHUNK_SIZE),
+      _ => self.push_left_main(Leaf(item), 2, 1),
+    }
+  }
+
+  /// add items into the last chunk, only nodes on the right edge are copied.
+  /// caller makes sure the chunk has room for them
+  pub(crate) fn push_right_chunk<I: ExactSizeIterator<Item = T>>(&self, items: I) -> Self {
+    match self {
+      Leaf(_) => unreachable!("expected a chunk at right edge"),
+      Chunk(xs) => {
+        let mut ys = Vec::with_capacity(xs.len() + items.len());
+        ys.extend_from_slice(xs);
+        ys.extend(items);
+        Chunk(ys)
+      }
+      Branch2 { size, left, middle, .. } => Branch2 {
+        size: size + items.len(),
+        left: left.to_owned(),
+        middle: Arc::new(middle.push_right_chunk(items)),
+        cache: NodeCache::new(),
+      },
+      Branch3 {
+        size, left, middle, right, ..
+      } => Branch3 {
+        size: size + items.len(),
+        left: left.to_owned(),
+        middle: middle.to_owned(),
+        right: Arc::new(right.push_right_chunk(items)),
+        cache: NodeCache::new(),
+      },
+    }
+  }
+
+  /// add item into the first chunk, only nodes on the left edge are copied
+  fn push_left_chunk(&self, item: T) -> Self {
+    match self {
+      Leaf(_) => unreachable!("expected a chunk at left edge"),
+      Chunk(xs) => {
+        let mut ys = Vec::with_capacity(xs.len() + 1);
+        ys.push(item);
+        ys.extend_from_slice(xs);
+        Chunk(ys)
+      }
+      Branch2 { size, left, middle, .. } => Branch2 {
+        size: size + 1,
+        left: Arc::new(left.push_left_chunk(item)),
+        middle: middle.to_owned(),
+        cache: NodeCache::new(),
+      },
+      Branch3 {
+        size, left, middle, right, ..
+      } => Branch3 {
+        size: size + 1,
+        left: Arc::new(left.push_left_chunk(item)),
+        middle: middle.to_owned(),
+        right: right.to_owned(),
+        cache: NodeCache::new(),
+      },
+    }
   }
 
   /// try to split a small bunch of elements under(or equal) a bound size,
@@ -253,10 +339,12 @@ where
     }
     match &self {
       Leaf(_) => (self.to_owned(), None),
+      Chunk(xs) => (Chunk(xs[..bound].to_vec()), Some(Chunk(xs[bound..].to_vec()))),
       Branch2 {
         size: root_size,
         left,
         middle,
+        ..
       } => {
         if left.len() <= bound {
           let (next_left_branch, rest_part) = middle.split_left_some(bound * 3);
@@ -267,6 +355,7 @@ where
                 size: middle.len(),
                 left: Arc::new(next_left_branch),
                 middle: Arc::new(branch),
+                cache: NodeCache::new(),
               }),
             ),
             None => ((**left).to_owned(), Some((**middle).to_owned())),
@@ -274,10 +363,20 @@ where
         } else {
           match &**left {
             Leaf(_) => unreachable!("leaf should already fall into prev case"),
+            Chunk(xs) => (
+              Chunk(xs[..bound].to_vec()),
+              Some(Branch2 {
+                size: root_size - bound,
+                left: Arc::new(Chunk(xs[bound..].to_vec())),
+                middle: middle.to_owned(),
+                cache: NodeCache::new(),
+              }),
+            ),
             Branch2 {
               size: _child_size,
               left: left_child,
               middle: middle_child,
+              ..
             } => {
               let (small_bunch, rest_node) = left_child.split_left_some(bound);
               match rest_node {
@@ -288,6 +387,7 @@ where
                     left: Arc::new(branch),
                     middle: middle_child.to_owned(),
                     right: middle.to_owned(),
+                    cache: NodeCache::new(),
                   }),
                 ),
                 None => (
@@ -296,6 +396,7 @@ where
                     size: root_size - left_child.len(),
                     left: middle_child.to_owned(),
                     middle: middle.to_owned(),
+                    cache: NodeCache::new(),
                   }),
                 ),
               }
@@ -305,6 +406,7 @@ where
               left: left_child,
               middle: middle_child,
               right: right_child,
+              ..
             } => {
               let (small_bunch, rest_node) = left_child.split_left_some(bound);
               match rest_node {
@@ -317,8 +419,10 @@ where
                       left: Arc::new(branch),
                       middle: middle_child.to_owned(),
                       right: right_child.to_owned(),
+                      cache: NodeCache::new(),
                     }),
                     middle: middle.to_owned(),
+                    cache: NodeCache::new(),
                   }),
                 ),
                 None => (
@@ -328,6 +432,7 @@ where
                     left: middle_child.to_owned(),
                     middle: right_child.to_owned(),
                     right: middle.to_owned(),
+                    cache: NodeCache::new(),
                   }),
                 ),
               }
@@ -340,6 +445,7 @@ where
         right,
         middle,
         left,
+        ..
       } => {
         if left.len() <= bound {
           let (next_left_branch, rest_part) = middle.split_left_some(bound * 3);
@@ -351,6 +457,7 @@ where
                 left: Arc::new(next_left_branch),
                 middle: Arc::new(branch),
                 right: right.to_owned(),
+                cache: NodeCache::new(),
               }),
             ),
             None => (
@@ -359,16 +466,28 @@ where
                 size: root_size - left.len(),
                 left: middle.to_owned(),
                 middle: right.to_owned(),
+                cache: NodeCache::new(),
               }),
             ),
           }
         } else {
           match &**left {
             Leaf(_) => unreachable!("leaf should already fall into prev case"),
+            Chunk(xs) => (
+              Chunk(xs[..bound].to_vec()),
+              Some(Branch3 {
+                size: root_size - bound,
+                left: Arc::new(Chunk(xs[bound..].to_vec())),
+                middle: middle.to_owned(),
+                right: right.to_owned(),
+                cache: NodeCache::new(),
+              }),
+            ),
             Branch2 {
               size: child_size,
               left: left_child,
               middle: middle_child,
+              ..
             } => {
               let (small_bunch, rest_node) = left_child.split_left_some(bound);
               match rest_node {
@@ -380,9 +499,11 @@ where
                       size: child_size - small_bunch.len(),
                       left: Arc::new(branch),
                       middle: middle_child.to_owned(),
+                      cache: NodeCache::new(),
                     }),
                     middle: middle.to_owned(),
                     right: right.to_owned(),
+                    cache: NodeCache::new(),
                   }),
                 ),
                 None => (
@@ -392,6 +513,7 @@ where
                     left: middle_child.to_owned(),
                     middle: middle.to_owned(),
                     right: right.to_owned(),
+                    cache: NodeCache::new(),
                   }),
                 ),
               }
@@ -401,6 +523,7 @@ where
               left: left_child,
               middle: middle_child,
               right: right_child,
+              ..
             } => {
               let (small_bunch, rest_node) = left_child.split_left_some(bound);
               match rest_node {
@@ -413,9 +536,11 @@ where
                       left: Arc::new(branch),
                       middle: middle_child.to_owned(),
                       right: right_child.to_owned(),
+                      cache: NodeCache::new(),
                     }),
                     middle: middle.to_owned(),
                     right: right.to_owned(),
+                    cache: NodeCache::new(),
                   }),
                 ),
                 None => (
@@ -426,9 +551,11 @@ where
                       size: child_size - small_bunch.len(),
                       left: middle_child.to_owned(),
                       middle: right_child.to_owned(),
+                      cache: NodeCache::new(),
                     }),
                     middle: middle.to_owned(),
                     right: right.to_owned(),
+                    cache: NodeCache::new(),
                   }),
                 ),
               }
@@ -445,10 +572,15 @@ where
     }
     match &self {
       Leaf(_) => (None, self.to_owned()),
+      Chunk(xs) => {
+        let pos = xs.len() - bound;
+        (Some(Chunk(xs[..pos].to_vec())), Chunk(xs[pos..].to_vec()))
+      }
       Branch2 {
         size: root_size,
         left,
         middle,
+        ..
       } => {
         if middle.len() <= bound {
           let (rest_part, next_right_branch) = left.split_right_some(bound * 3);
@@ -458,6 +590,7 @@ where
                 size: left.len(),
                 left: Arc::new(branch),
                 middle: Arc::new(next_right_branch),
+                cache: NodeCache::new(),
               }),
               (**middle).to_owned(),
             ),
@@ -466,10 +599,23 @@ where
         } else {
           match &**middle {
             Leaf(_) => unreachable!("leaf should already fall into prev case"),
+            Chunk(xs) => {
+              let pos = xs.len() - bound;
+              (
+                Some(Branch2 {
+                  size: root_size - bound,
+                  left: left.to_owned(),
+                  middle: Arc::new(Chunk(xs[..pos].to_vec())),
+                  cache: NodeCache::new(),
+                }),
+                Chunk(xs[pos..].to_vec()),
+              )
+            }
             Branch2 {
               size: _child_size,
               left: left_child,
               middle: middle_child,
+              ..
             } => {
               let (rest_node, small_bunch) = middle_child.split_right_some(bound);
               match rest_node {
@@ -479,6 +625,7 @@ where
                     left: left.to_owned(),
                     middle: left_child.to_owned(),
                     right: Arc::new(branch),
+                    cache: NodeCache::new(),
                   }),
                   small_bunch,
                 ),
@@ -487,6 +634,7 @@ where
                     size: root_size - middle_child.len(),
                     left: left.to_owned(),
                     middle: left_child.to_owned(),
+                    cache: NodeCache::new(),
                   }),
                   (**middle_child).to_owned(),
                 ),
@@ -497,6 +645,7 @@ where
               left: left_child,
               middle: middle_child,
               right: right_child,
+              ..
             } => {
               let (rest_node, small_bunch) = right_child.split_right_some(bound);
               match rest_node {
@@ -509,7 +658,9 @@ where
                       left: left_child.to_owned(),
                       middle: middle_child.to_owned(),
                       right: Arc::new(branch),
+                      cache: NodeCache::new(),
                     }),
+                    cache: NodeCache::new(),
                   }),
                   small_bunch,
                 ),
@@ -519,6 +670,7 @@ where
                     left: left.to_owned(),
                     middle: left_child.to_owned(),
                     right: middle_child.to_owned(),
+                    cache: NodeCache::new(),
                   }),
                   small_bunch,
                 ),
@@ -532,6 +684,7 @@ where
         left,
         middle,
         right,
+        ..
       } => {
         if right.len() <= bound {
           let (rest_part, next_right_branch) = middle.split_right_some(bound * 3);
@@ -542,6 +695,7 @@ where
                 left: left.to_owned(),
                 middle: Arc::new(branch),
                 right: Arc::new(next_right_branch),
+                cache: NodeCache::new(),
               }),
               (**right).to_owned(),
             ),
@@ -550,6 +704,7 @@ where
                 size: root_size - right.len(),
                 left: left.to_owned(),
                 middle: middle.to_owned(),
+                cache: NodeCache::new(),
               }),
               (**right).to_owned(),
             ),
@@ -557,10 +712,24 @@ where
         } else {
           match &**right {
             Leaf(_) => unreachable!("leaf should already fall into prev case"),
+            Chunk(xs) => {
+              let pos = xs.len() - bound;
+              (
+                Some(Branch3 {
+                  size: root_size - bound,
+                  left: left.to_owned(),
+                  middle: middle.to_owned(),
+                  right: Arc::new(Chunk(xs[..pos].to_vec())),
+                  cache: NodeCache::new(),
+                }),
+                Chunk(xs[pos..].to_vec()),
+              )
+            }
             Branch2 {
               size: child_size,
               left: left_child,
               middle: middle_child,
+              ..
             } => {
               let (rest_node, small_bunch) = middle_child.split_right_some(bound);
               match rest_node {
@@ -573,7 +742,9 @@ where
                       size: child_size - small_bunch.len(),
                       left: left_child.to_owned(),
                       middle: Arc::new(branch),
+                      cache: NodeCache::new(),
                     }),
+                    cache: NodeCache::new(),
                   }),
                   small_bunch,
                 ),
@@ -583,6 +754,7 @@ where
                     left: left.to_owned(),
                     middle: middle.to_owned(),
                     right: left_child.to_owned(),
+                    cache: NodeCache::new(),
                   }),
                   (**middle_child).to_owned(),
                 ),
@@ -593,6 +765,7 @@ where
               left: left_child,
               middle: middle_child,
               right: right_child,
+              ..
             } => {
               let (rest_node, small_bunch) = right_child.split_right_some(bound);
               match rest_node {
@@ -606,7 +779,9 @@ where
                       left: left_child.to_owned(),
                       middle: middle_child.to_owned(),
                       right: Arc::new(branch),
+                      cache: NodeCache::new(),
                     }),
+                    cache: NodeCache::new(),
                   }),
                   small_bunch,
                 ),
@@ -619,7 +794,9 @@ where
                       size: child_size - small_bunch.len(),
                       left: left_child.to_owned(),
                       middle: middle_child.to_owned(),
+                      cache: NodeCache::new(),
                     }),
+                    cache: NodeCache::new(),
                   }),
                   small_bunch,
                 ),
@@ -632,16 +809,22 @@ where
   }
 
   pub fn drop_left(&self) -> Self {
+    self.pop_left().1
+  }
+
+  /// first element together with the rest, in one descent. not for a single leaf
+  pub fn pop_left(&self) -> (&T, Self) {
     match self {
       Leaf(_) => {
         unreachable!("not expected empty node inside tree")
       }
+      Chunk(xs) => (&xs[0], Chunk(xs[1..].to_vec())),
       Branch2 { size, left, middle, .. } => {
         if left.len() == 1 {
-          (**middle).to_owned()
+          (left.loop_first(), (**middle).to_owned())
         } else {
-          let changed_branch = left.drop_left();
-          match changed_branch {
+          let (item, changed_branch) = left.pop_left();
+          let rest = match changed_branch {
             Branch2 {
               left: b_left,
               middle: b_middle,
@@ -651,6 +834,7 @@ where
               left: b_left,
               middle: b_middle,
               right: middle.to_owned(),
+              cache: NodeCache::new(),
             },
             Branch3 {
               left: b_left,
@@ -662,27 +846,31 @@ where
                 size: b_middle.len() + b_right.len(),
                 left: b_middle,
                 middle: b_right,
+                cache: NodeCache::new(),
               };
               Branch3 {
                 size: size - 1,
                 left: b_left,
                 middle: Arc::new(internal_branch),
                 right: middle.to_owned(),
+                cache: NodeCache::new(),
               }
             }
             _ => Branch2 {
               size: size - 1,
               left: Arc::new(changed_branch),
               middle: middle.to_owned(),
+              cache: NodeCache::new(),
             },
-          }
+          };
+          (item, rest)
         }
       }
       Branch3 {
         size, left, middle, right, ..
       } => {
         if left.len() == 1 {
-          match &**middle {
+          let rest = match &**middle {
             Branch2 {
               left: b_left,
               middle: b_middle,
@@ -692,6 +880,7 @@ where
               left: b_left.to_owned(),
               middle: b_middle.to_owned(),
               right: right.to_owned(),
+              cache: NodeCache::new(),
             },
             Branch3 {
               left: b_left,
@@ -703,44 +892,56 @@ where
                 size: b_middle.len() + b_right.len(),
                 left: b_middle.to_owned(),
                 middle: b_right.to_owned(),
+                cache: NodeCache::new(),
               };
               Branch3 {
                 size: size - 1,
                 left: b_left.to_owned(),
                 middle: Arc::new(internal_branch),
                 right: right.to_owned(),
+                cache: NodeCache::new(),
               }
             }
             _ => Branch2 {
               size: size - 1,
               left: middle.to_owned(),
               middle: right.to_owned(),
+              cache: NodeCache::new(),
             },
-          }
+          };
+          (left.loop_first(), rest)
         } else {
-          let changed_branch = left.drop_left();
-          Branch3 {
+          let (item, changed_branch) = left.pop_left();
+          let rest = Branch3 {
             size: size - 1,
             left: Arc::new(changed_branch),
             middle: middle.to_owned(),
             right: right.to_owned(),
-          }
+            cache: NodeCache::new(),
+          };
+          (item, rest)
         }
       }
     }
   }
 
   pub fn drop_right(&self) -> Self {
+    self.pop_right().1
+  }
+
+  /// last element together with the rest, in one descent. not for a single leaf
+  pub fn pop_right(&self) -> (&T, Self) {
     match self {
       Leaf(_) => {
         unreachable!("not expected empty node inside tree")
       }
+      Chunk(xs) => (&xs[xs.len() - 1], Chunk(xs[..xs.len() - 1].to_vec())),
       Branch2 { size, left, middle, .. } => {
         if middle.len() == 1 {
-          (**left).to_owned()
+          (middle.loop_last(), (**left).to_owned())
         } else {
-          let changed_branch = middle.drop_right();
-          match changed_branch {
+          let (item, changed_branch) = middle.pop_right();
+          let rest = match changed_branch {
             Branch2 {
               left: b_left,
               middle: b_middle,
@@ -750,6 +951,7 @@ where
               left: left.to_owned(),
               middle: b_left,
               right: b_middle,
+              cache: NodeCache::new(),
             },
             Branch3 {
               left: b_left,
@@ -761,27 +963,31 @@ where
                 size: b_middle.len() + b_left.len(),
                 left: b_left,
                 middle: b_middle,
+                cache: NodeCache::new(),
               };
               Branch3 {
                 size: size - 1,
                 left: left.to_owned(),
                 middle: Arc::new(internal_branch),
                 right: b_right,
+                cache: NodeCache::new(),
               }
             }
             _ => Branch2 {
               size: size - 1,
               left: left.to_owned(),
               middle: Arc::new(changed_branch),
+              cache: NodeCache::new(),
             },
-          }
+          };
+          (item, rest)
         }
       }
       Branch3 {
         size, right, middle, left, ..
       } => {
         if right.len() == 1 {
-          match &**middle {
+          let rest = match &**middle {
             Branch2 {
               left: b_left,
               middle: b_middle,
@@ -791,6 +997,7 @@ where
               left: left.to_owned(),
               middle: b_left.to_owned(),
               right: b_middle.to_owned(),
+              cache: NodeCache::new(),
             },
             Branch3 {
               left: b_left,
@@ -802,28 +1009,34 @@ where
                 size: b_middle.len() + b_left.len(),
                 left: b_left.to_owned(),
                 middle: b_middle.to_owned(),
+                cache: NodeCache::new(),
               };
               Branch3 {
                 size: size - 1,
                 left: left.to_owned(),
                 middle: Arc::new(internal_branch),
                 right: b_right.to_owned(),
+                cache: NodeCache::new(),
               }
             }
             _ => Branch2 {
               size: size - 1,
               left: left.to_owned(),
               middle: middle.to_owned(),
+              cache: NodeCache::new(),
             },
-          }
+          };
+          (right.loop_last(), rest)
         } else {
-          let changed_branch = right.drop_right();
-          Branch3 {
+          let (item, changed_branch) = right.pop_right();
+          let rest = Branch3 {
             size: size - 1,
             left: left.to_owned(),
             middle: middle.to_owned(),
             right: Arc::new(changed_branch),
-          }
+            cache: NodeCache::new(),
+          };
+          (item, rest)
         }
       }
     }
@@ -0,0 +1,84 @@
+//! multi-line dump of a tree, one node per line, children indented below their parents like:
+//!
+//! ```text
+//! Branch3 size=5 depth=0
+//! |-- Leaf 1
+//! |-- Branch3 size=3 depth=1
+//! |   |-- Leaf 2
+//! |   |-- Leaf 3
+//! |   `-- Leaf 4
+//! `-- Leaf 5
+//! ```
+
+use super::TernaryTree::{self, *};
+
+use std::fmt::{Display, Write};
+
+/// options for `format_tree`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FormatTreeOptions {
+  /// branches at this depth are printed without children, `None` to print the whole tree
+  pub max_depth: Option<usize>,
+  /// print index of the first element in each node, like `@10`
+  pub offsets: bool,
+}
+
+impl<T: Display> TernaryTree<T> {
+  pub fn format_tree(&self, opts: &FormatTreeOptions) -> String {
+    let mut out = String::new();
+    self.write_tree(&mut out, "", "", 0, 0, opts);
+    out
+  }
+
+  /// `head` goes before this node, and `indent` before its children
+  fn write_tree(&self, out: &mut String, head: &str, indent: &str, depth: usize, offset: usize, opts: &FormatTreeOptions) {
+    out.push_str(head);
+    match self {
+      Leaf(value) => {
+        let _ = write!(out, "Leaf {value}");
+      }
+      Chunk(xs) => {
+        let values = xs.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(" ");
+        let _ = write!(out, "Chunk size={} depth={depth} [{values}]", xs.len());
+      }
+      Branch2 { size, .. } => {
+        let _ = write!(out, "Branch2 size={size} depth={depth}");
+      }
+      Branch3 { size, .. } => {
+        let _ = write!(out, "Branch3 size={size} depth={depth}");
+      }
+    }
+    if opts.offsets {
+      let _ = write!(out, " @{offset}");
+    }
+
+    let children: Vec<&Self> = match self {
+      Leaf(_) | Chunk(_) => vec![],
+      Branch2 { left, middle, .. } => vec![left, middle],
+      Branch3 { left, middle, right, .. } => vec![left, middle, right],
+    };
+    if !children.is_empty() && opts.max_depth.is_some_and(|max| depth >= max) {
+      out.push_str(" ...\n");
+      return;
+    }
+    out.push('\n');
+
+    let mut child_offset = offset;
+    for (idx, child) in children.iter().enumerate() {
+      let (head, next) = if idx + 1 == children.len() {
+        ("`-- ", "    ")
+      } else {
+        ("|-- ", "|   ")
+      };
+      child.write_tree(
+        out,
+        &format!("{indent}{head}"),
+        &format!("{indent}{next}"),
+        depth + 1,
+        child_offset,
+        opts,
+      );
+      child_offset += child.len();
+    }
+  }
+}
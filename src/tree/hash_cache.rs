@@ -0,0 +1,68 @@
+//! content-defined hash cached inside branches.
+//!
+//! hashes of elements are combined like a polynomial `h0 * B^(n-1) + h1 * B^(n-2) + ... + h(n-1)` modulo a Mersenne prime,
+//! so joining two parts only requires the digests of both parts and `B^len` of the right part.
+//! the result does not depend on the shape of the tree, and each branch caches the digest of its own elements.
+//! after `assoc` or `push_right`, only newly created branches on the changed path are computed again.
+
+use super::TernaryTree::{self, *};
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+const MODULUS: u64 = (1 << 61) - 1;
+const BASE: u64 = 0x0100_0000_01b3;
+
+fn add_mod(a: u64, b: u64) -> u64 {
+  let x = a + b;
+  if x >= MODULUS { x - MODULUS } else { x }
+}
+
+fn mul_mod(a: u64, b: u64) -> u64 {
+  let x = (a as u128) * (b as u128);
+  add_mod((x as u64) & MODULUS, (x >> 61) as u64)
+}
+
+/// digest of left part followed by right part
+fn join((h1, p1): (u64, u64), (h2, p2): (u64, u64)) -> (u64, u64) {
+  (add_mod(mul_mod(h1, p2), h2), mul_mod(p1, p2))
+}
+
+fn element_digest<T: Hash>(value: &T) -> u64 {
+  let mut hasher = DefaultHasher::new();
+  value.hash(&mut hasher);
+  hasher.finish() % MODULUS
+}
+
+/// mix digest with size into the final hash
+pub(crate) fn finish_digest(digest: u64, size: usize) -> u64 {
+  let mut hasher = DefaultHasher::new();
+  hasher.write_u64(digest);
+  hasher.write_usize(size);
+  hasher.finish()
+}
+
+impl<T: Hash> TernaryTree<T> {
+  /// `(digest, BASE^len)` of elements, branches compute it only once
+  fn digest(&self) -> (u64, u64) {
+    match self {
+      Leaf(value) => (element_digest(value), BASE),
+      Chunk(xs) => xs.iter().fold((0, 1), |acc, x| join(acc, (element_digest(x), BASE))),
+      Branch2 { left, middle, cache, .. } => *cache.digest.get_or_init(|| join(left.digest(), middle.digest())),
+      Branch3 {
+        left,
+        middle,
+        right,
+        cache,
+        ..
+      } => *cache
+        .digest
+        .get_or_init(|| join(join(left.digest(), middle.digest()), right.digest())),
+    }
+  }
+
+  /// hash decided only by elements, trees of different shapes get same value when elements are equal.
+  /// digests are cached in branches, so hashing again after a small change only visits the changed path
+  pub fn cached_hash(&self) -> u64 {
+    finish_digest(self.digest().0, self.len())
+  }
+}
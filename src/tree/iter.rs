@@ -0,0 +1,346 @@
+//! iterators walking leaves of the tree.
+//! a stack of pending branches is kept, so each node is visited once during a full iteration,
+//! instead of drilling down from the root for every index.
+//! the owning iterator moves values out of nodes that are not shared with other trees.
+//! elements of a chunk are taken one by one from the chunk reached at each side.
+
+use super::TernaryTree::{self, *};
+
+use std::collections::VecDeque;
+use std::iter::FusedIterator;
+use std::slice;
+use std::sync::Arc;
+use std::vec;
+
+/// iterator over references of elements, works from both ends.
+/// each stack holds subtrees that are not visited yet from its side, with the next subtree at the top.
+/// a stack is seeded with root lazily, and `remaining` tells when two sides meet
+pub struct TernaryTreeIterator<'a, T> {
+  root: &'a TernaryTree<T>,
+  front: Vec<&'a TernaryTree<T>>,
+  back: Vec<&'a TernaryTree<T>>,
+  /// elements left in the chunk reached from front side
+  front_chunk: slice::Iter<'a, T>,
+  back_chunk: slice::Iter<'a, T>,
+  remaining: usize,
+}
+
+impl<'a, T> TernaryTreeIterator<'a, T> {
+  pub fn new(tree: &'a TernaryTree<T>) -> Self {
+    TernaryTreeIterator {
+      root: tree,
+      front: vec![],
+      back: vec![],
+      front_chunk: Default::default(),
+      back_chunk: Default::default(),
+      remaining: tree.len(),
+    }
+  }
+
+  /// iterate over elements in `start..end`, positions of both ends are located by going down from root once.
+  /// range is not checked here
+  pub fn new_range(tree: &'a TernaryTree<T>, start: usize, end: usize) -> Self {
+    let mut it = Self::new(tree);
+    it.remaining = end - start;
+    if start < end {
+      it.front_chunk = seek_front(tree, start, &mut it.front);
+      it.back_chunk = seek_back(tree, end - 1, &mut it.back);
+    }
+    it
+  }
+}
+
+/// prepare stack for visiting from `idx` to the end, siblings on the right are remembered while going down.
+/// returns rest of the chunk when `idx` falls inside a chunk
+fn seek_front<'a, T>(tree: &'a TernaryTree<T>, idx: usize, stack: &mut Vec<&'a TernaryTree<T>>) -> slice::Iter<'a, T> {
+  let mut node = tree;
+  let mut idx = idx;
+  loop {
+    match node {
+      Leaf(_) => {
+        stack.push(node);
+        return Default::default();
+      }
+      Chunk(xs) => return xs[idx..].iter(),
+      Branch2 { left, middle, .. } => {
+        if idx < left.len() {
+          stack.push(middle);
+          node = left;
+        } else {
+          idx -= left.len();
+          node = middle;
+        }
+      }
+      Branch3 { left, middle, right, .. } => {
+        if idx < left.len() {
+          stack.push(right);
+          stack.push(middle);
+          node = left;
+        } else if idx < left.len() + middle.len() {
+          idx -= left.len();
+          stack.push(right);
+          node = middle;
+        } else {
+          idx -= left.len() + middle.len();
+          node = right;
+        }
+      }
+    }
+  }
+}
+
+/// prepare stack for visiting from `idx` back to the start, siblings on the left are remembered while going down.
+/// returns leading part of the chunk when `idx` falls inside a chunk
+fn seek_back<'a, T>(tree: &'a TernaryTree<T>, idx: usize, stack: &mut Vec<&'a TernaryTree<T>>) -> slice::Iter<'a, T> {
+  let mut node = tree;
+  let mut idx = idx;
+  loop {
+    match node {
+      Leaf(_) => {
+        stack.push(node);
+        return Default::default();
+      }
+      Chunk(xs) => return xs[..=idx].iter(),
+      Branch2 { left, middle, .. } => {
+        if idx < left.len() {
+          node = left;
+        } else {
+          idx -= left.len();
+          stack.push(left);
+          node = middle;
+        }
+      }
+      Branch3 { left, middle, right, .. } => {
+        if idx < left.len() {
+          node = left;
+        } else if idx < left.len() + middle.len() {
+          idx -= left.len();
+          stack.push(left);
+          node = middle;
+        } else {
+          idx -= left.len() + middle.len();
+          stack.push(left);
+          stack.push(middle);
+          node = right;
+        }
+      }
+    }
+  }
+}
+
+impl<'a, T> Iterator for TernaryTreeIterator<'a, T> {
+  type Item = &'a T;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.remaining == 0 {
+      return None;
+    }
+    if let Some(value) = self.front_chunk.next() {
+      self.remaining -= 1;
+      return Some(value);
+    }
+    // stack only runs out after all elements are visited from this side
+    let mut node = self.front.pop().unwrap_or(self.root);
+    // go down along left branches, remember siblings for later
+    loop {
+      match node {
+        Leaf(value) => {
+          self.remaining -= 1;
+          return Some(value);
+        }
+        Chunk(xs) => {
+          self.front_chunk = xs.iter();
+          self.remaining -= 1;
+          return self.front_chunk.next();
+        }
+        Branch2 { left, middle, .. } => {
+          self.front.push(middle);
+          node = left;
+        }
+        Branch3 { left, middle, right, .. } => {
+          self.front.push(right);
+          self.front.push(middle);
+          node = left;
+        }
+      }
+    }
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.remaining, Some(self.remaining))
+  }
+}
+
+impl<T> DoubleEndedIterator for TernaryTreeIterator<'_, T> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    if self.remaining == 0 {
+      return None;
+    }
+    if let Some(value) = self.back_chunk.next_back() {
+      self.remaining -= 1;
+      return Some(value);
+    }
+    let mut node = self.back.pop().unwrap_or(self.root);
+    // go down along right branches, remember siblings for later
+    loop {
+      match node {
+        Leaf(value) => {
+          self.remaining -= 1;
+          return Some(value);
+        }
+        Chunk(xs) => {
+          self.back_chunk = xs.iter();
+          self.remaining -= 1;
+          return self.back_chunk.next_back();
+        }
+        Branch2 { left, middle, .. } => {
+          self.back.push(left);
+          node = middle;
+        }
+        Branch3 { left, middle, right, .. } => {
+          self.back.push(left);
+          self.back.push(middle);
+          node = right;
+        }
+      }
+    }
+  }
+}
+
+impl<T> ExactSizeIterator for TernaryTreeIterator<'_, T> {}
+
+impl<T> FusedIterator for TernaryTreeIterator<'_, T> {}
+
+impl<'a, T> IntoIterator for &'a TernaryTree<T> {
+  type Item = &'a T;
+  type IntoIter = TernaryTreeIterator<'a, T>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    TernaryTreeIterator::new(self)
+  }
+}
+
+/// iterator that consumes the tree and yields owned elements.
+/// subtrees not visited yet are kept in order in a deque, so both ends can take from it.
+/// a node is taken out of its `Arc` when it's uniquely owned, shared nodes are copied(children are only ref-counted)
+pub struct TernaryTreeIntoIterator<T> {
+  pending: VecDeque<Arc<TernaryTree<T>>>,
+  /// elements left in the chunk taken from front side
+  front_chunk: vec::IntoIter<T>,
+  back_chunk: vec::IntoIter<T>,
+  remaining: usize,
+}
+
+impl<T> TernaryTreeIntoIterator<T> {
+  pub fn new(tree: TernaryTree<T>) -> Self {
+    let remaining = tree.len();
+    let mut pending = VecDeque::with_capacity(16);
+    pending.push_back(Arc::new(tree));
+    TernaryTreeIntoIterator {
+      pending,
+      front_chunk: Default::default(),
+      back_chunk: Default::default(),
+      remaining,
+    }
+  }
+}
+
+/// move node out of `Arc` when it's not shared, otherwise clone
+fn unwrap_node<T: Clone>(node: Arc<TernaryTree<T>>) -> TernaryTree<T> {
+  Arc::try_unwrap(node).unwrap_or_else(|shared| (*shared).to_owned())
+}
+
+impl<T: Clone> Iterator for TernaryTreeIntoIterator<T> {
+  type Item = T;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    loop {
+      if let Some(value) = self.front_chunk.next() {
+        self.remaining -= 1;
+        return Some(value);
+      }
+      let Some(mut node) = self.pending.pop_front() else {
+        // the rest are in the chunk taken by back side
+        let value = self.back_chunk.next()?;
+        self.remaining -= 1;
+        return Some(value);
+      };
+      loop {
+        match unwrap_node(node) {
+          Leaf(value) => {
+            self.remaining -= 1;
+            return Some(value);
+          }
+          Chunk(xs) => {
+            self.front_chunk = xs.into_iter();
+            break;
+          }
+          Branch2 { left, middle, .. } => {
+            self.pending.push_front(middle);
+            node = left;
+          }
+          Branch3 { left, middle, right, .. } => {
+            self.pending.push_front(right);
+            self.pending.push_front(middle);
+            node = left;
+          }
+        }
+      }
+    }
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.remaining, Some(self.remaining))
+  }
+}
+
+impl<T: Clone> DoubleEndedIterator for TernaryTreeIntoIterator<T> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    loop {
+      if let Some(value) = self.back_chunk.next_back() {
+        self.remaining -= 1;
+        return Some(value);
+      }
+      let Some(mut node) = self.pending.pop_back() else {
+        // the rest are in the chunk taken by front side
+        let value = self.front_chunk.next_back()?;
+        self.remaining -= 1;
+        return Some(value);
+      };
+      loop {
+        match unwrap_node(node) {
+          Leaf(value) => {
+            self.remaining -= 1;
+            return Some(value);
+          }
+          Chunk(xs) => {
+            self.back_chunk = xs.into_iter();
+            break;
+          }
+          Branch2 { left, middle, .. } => {
+            self.pending.push_back(left);
+            node = middle;
+          }
+          Branch3 { left, middle, right, .. } => {
+            self.pending.push_back(left);
+            self.pending.push_back(middle);
+            node = right;
+          }
+        }
+      }
+    }
+  }
+}
+
+impl<T: Clone> ExactSizeIterator for TernaryTreeIntoIterator<T> {}
+
+impl<T: Clone> FusedIterator for TernaryTreeIntoIterator<T> {}
+
+impl<T: Clone> IntoIterator for TernaryTree<T> {
+  type Item = T;
+  type IntoIter = TernaryTreeIntoIterator<T>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    TernaryTreeIntoIterator::new(self)
+  }
+}
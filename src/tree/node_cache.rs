@@ -0,0 +1,28 @@
+//! values computed from a branch once and kept inside it, since branches are never changed after sharing.
+
+use std::sync::OnceLock;
+
+/// cached values of a branch, empty until first used.
+/// all of them are dropped together when a branch is edited in place
+#[derive(Clone, Debug, Default)]
+pub struct NodeCache {
+  /// `(digest, BASE^len)` of elements, see `hash_cache`
+  #[cfg(feature = "cached-hash")]
+  pub(super) digest: OnceLock<(u64, u64)>,
+  /// levels of branches above the deepest leaf
+  depth: OnceLock<usize>,
+}
+
+impl NodeCache {
+  pub fn new() -> Self {
+    NodeCache {
+      #[cfg(feature = "cached-hash")]
+      digest: OnceLock::new(),
+      depth: OnceLock::new(),
+    }
+  }
+
+  pub(crate) fn depth(&self, f: impl FnOnce() -> usize) -> usize {
+    *self.depth.get_or_init(f)
+  }
+}
@@ -0,0 +1,128 @@
+//! reading `format_inline` text back into a tree of the same shape,
+//! `(a b)` for `Branch2`, `(a b c)` for `Branch3`, `[a b]` for chunks, and other words for leaves.
+//! values are split by whitespace and brackets, so values printed with them do not round trip.
+
+use super::TernaryTree::{self, *};
+use super::{CHUNK_SIZE, MAX_READ_DEPTH, NodeCache};
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::error::ParseInlineError;
+
+struct InlineParser<'a> {
+  text: &'a str,
+  /// byte offset of next char
+  pos: usize,
+}
+
+impl InlineParser<'_> {
+  fn skip_spaces(&mut self) {
+    let rest = &self.text[self.pos..];
+    self.pos += rest.len() - rest.trim_start().len();
+  }
+
+  fn peek(&self) -> Option<char> {
+    self.text[self.pos..].chars().next()
+  }
+
+  fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '(' | ')' | '[' | ']')
+  }
+
+  fn parse_value<T: FromStr>(&mut self) -> Result<T, ParseInlineError> {
+    let start = self.pos;
+    let rest = &self.text[start..];
+    let end = rest.find(Self::is_delimiter).unwrap_or(rest.len());
+    self.pos += end;
+    rest[..end].parse().map_err(|_| ParseInlineError::InvalidValue { offset: start })
+  }
+
+  /// `depth` counts branches opened above this node
+  fn parse_node<T: FromStr>(&mut self, depth: usize) -> Result<TernaryTree<T>, ParseInlineError> {
+    self.skip_spaces();
+    let start = self.pos;
+    match self.peek() {
+      None => Err(ParseInlineError::UnexpectedEnd),
+      Some('(') => {
+        if depth >= MAX_READ_DEPTH {
+          return Err(ParseInlineError::TooDeep { offset: start });
+        }
+        self.pos += 1;
+        let mut children: Vec<TernaryTree<T>> = Vec::with_capacity(3);
+        loop {
+          self.skip_spaces();
+          match self.peek() {
+            None => return Err(ParseInlineError::UnexpectedEnd),
+            Some(')') => {
+              self.pos += 1;
+              break;
+            }
+            Some(_) => children.push(self.parse_node(depth + 1)?),
+          }
+        }
+        let size = children.iter().map(|x| x.len()).sum();
+        let count = children.len();
+        let mut nodes = children.into_iter().map(Arc::new);
+        match (nodes.next(), nodes.next(), nodes.next()) {
+          (Some(left), Some(middle), None) => Ok(Branch2 {
+            size,
+            left,
+            middle,
+            cache: NodeCache::new(),
+          }),
+          (Some(left), Some(middle), Some(right)) if count == 3 => Ok(Branch3 {
+            size,
+            left,
+            middle,
+            right,
+            cache: NodeCache::new(),
+          }),
+          _ => Err(ParseInlineError::InvalidBranch {
+            offset: start,
+            children: count,
+          }),
+        }
+      }
+      Some('[') => {
+        self.pos += 1;
+        let mut xs: Vec<T> = vec![];
+        loop {
+          self.skip_spaces();
+          match self.peek() {
+            None => return Err(ParseInlineError::UnexpectedEnd),
+            Some(']') => {
+              self.pos += 1;
+              break;
+            }
+            Some('(' | ')' | '[') => return Err(ParseInlineError::UnexpectedChar { offset: self.pos }),
+            Some(_) => xs.push(self.parse_value()?),
+          }
+        }
+        if xs.is_empty() || xs.len() > CHUNK_SIZE {
+          Err(ParseInlineError::InvalidChunk {
+            offset: start,
+            size: xs.len(),
+          })
+        } else {
+          Ok(Chunk(xs))
+        }
+      }
+      Some(')' | ']') => Err(ParseInlineError::UnexpectedChar { offset: start }),
+      Some(_) => Ok(Leaf(self.parse_value()?)),
+    }
+  }
+}
+
+impl<T: FromStr> TernaryTree<T> {
+  /// parse text from `format_inline`, every node is built as written without rebalancing
+  pub fn parse_inline(text: &str) -> Result<Self, ParseInlineError> {
+    let mut parser = InlineParser { text, pos: 0 };
+    let tree = parser.parse_node(0)?;
+    parser.skip_spaces();
+    if parser.pos < text.len() {
+      return Err(ParseInlineError::UnexpectedChar { offset: parser.pos });
+    }
+    Ok(tree)
+  }
+}
@@ -0,0 +1,265 @@
+//! binary snapshots of several lists, nodes are written once and referred to by ids,
+//! so subtrees shared by `Arc` are shared again after loading.
+//!
+//! layout, numbers are unsigned LEB128:
+//!
+//! ```text
+//! magic "TTS" version 1
+//! node count, nodes, children always come before parents
+//!   0 len bytes                   Leaf
+//!   1 count (len bytes)*          Chunk
+//!   2 left middle                 Branch2, ids of children
+//!   3 left middle right           Branch3
+//! list count, (0 for empty list | root id + 1)*
+//! ```
+
+use super::TernaryTree::{self, *};
+use super::{CHUNK_SIZE, MAX_READ_DEPTH, NodeCache};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::error::SnapshotError;
+use crate::snapshot::SnapshotCodec;
+
+const MAGIC: &[u8; 4] = b"TTS\x01";
+
+const TAG_LEAF: u8 = 0;
+const TAG_CHUNK: u8 = 1;
+const TAG_BRANCH2: u8 = 2;
+const TAG_BRANCH3: u8 = 3;
+
+/// counts in a snapshot are not trusted for allocating
+const MAX_PREALLOCATE: usize = 4096;
+
+fn write_number(out: &mut Vec<u8>, mut n: usize) {
+  while n >= 0x80 {
+    out.push((n as u8 & 0x7f) | 0x80);
+    n >>= 7;
+  }
+  out.push(n as u8);
+}
+
+struct SnapshotWriter<'a, T, C> {
+  codec: &'a C,
+  ids: HashMap<*const TernaryTree<T>, usize>,
+  count: usize,
+  body: Vec<u8>,
+  /// reused for encoding each element before its length is known
+  scratch: Vec<u8>,
+}
+
+impl<T, C: SnapshotCodec<T>> SnapshotWriter<'_, T, C> {
+  fn write_value(&mut self, item: &T) {
+    self.scratch.clear();
+    self.codec.encode(item, &mut self.scratch);
+    write_number(&mut self.body, self.scratch.len());
+    self.body.extend_from_slice(&self.scratch);
+  }
+
+  fn add_node(&mut self, tree: &TernaryTree<T>) -> usize {
+    match tree {
+      Leaf(value) => {
+        self.body.push(TAG_LEAF);
+        self.write_value(value);
+      }
+      Chunk(xs) => {
+        self.body.push(TAG_CHUNK);
+        write_number(&mut self.body, xs.len());
+        for x in xs {
+          self.write_value(x);
+        }
+      }
+      Branch2 { left, middle, .. } => {
+        let ids = [self.add_child(left), self.add_child(middle)];
+        self.body.push(TAG_BRANCH2);
+        for id in ids {
+          write_number(&mut self.body, id);
+        }
+      }
+      Branch3 { left, middle, right, .. } => {
+        let ids = [self.add_child(left), self.add_child(middle), self.add_child(right)];
+        self.body.push(TAG_BRANCH3);
+        for id in ids {
+          write_number(&mut self.body, id);
+        }
+      }
+    }
+    let id = self.count;
+    self.count += 1;
+    id
+  }
+
+  fn add_child(&mut self, child: &Arc<TernaryTree<T>>) -> usize {
+    let ptr = Arc::as_ptr(child);
+    if let Some(id) = self.ids.get(&ptr) {
+      return *id;
+    }
+    let id = self.add_node(child);
+    self.ids.insert(ptr, id);
+    id
+  }
+}
+
+struct SnapshotReader<'a> {
+  bytes: &'a [u8],
+  pos: usize,
+}
+
+impl<'a> SnapshotReader<'a> {
+  fn read_byte(&mut self) -> Result<u8, SnapshotError> {
+    let byte = *self.bytes.get(self.pos).ok_or(SnapshotError::UnexpectedEnd)?;
+    self.pos += 1;
+    Ok(byte)
+  }
+
+  fn read_number(&mut self) -> Result<usize, SnapshotError> {
+    let start = self.pos;
+    let mut n: usize = 0;
+    let mut shift = 0;
+    loop {
+      let byte = self.read_byte()?;
+      let bits = (byte & 0x7f) as usize;
+      if shift >= usize::BITS || (bits << shift) >> shift != bits {
+        return Err(SnapshotError::InvalidNumber { offset: start });
+      }
+      n |= bits << shift;
+      if byte & 0x80 == 0 {
+        return Ok(n);
+      }
+      shift += 7;
+    }
+  }
+
+  fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], SnapshotError> {
+    let end = self.pos.checked_add(len).filter(|end| *end <= self.bytes.len());
+    let bytes = &self.bytes[self.pos..end.ok_or(SnapshotError::UnexpectedEnd)?];
+    self.pos += len;
+    Ok(bytes)
+  }
+
+  fn read_value<T, C: SnapshotCodec<T>>(&mut self, codec: &C) -> Result<T, SnapshotError> {
+    let len = self.read_number()?;
+    let offset = self.pos;
+    codec.decode(self.read_bytes(len)?).ok_or(SnapshotError::InvalidValue { offset })
+  }
+
+  /// id of a node that is already read
+  fn read_id<T>(&mut self, nodes: &[Arc<TernaryTree<T>>]) -> Result<Arc<TernaryTree<T>>, SnapshotError> {
+    let offset = self.pos;
+    let id = self.read_number()?;
+    nodes.get(id).cloned().ok_or(SnapshotError::InvalidReference { offset, id })
+  }
+
+  fn read_node<T, C: SnapshotCodec<T>>(&mut self, codec: &C, nodes: &[Arc<TernaryTree<T>>]) -> Result<TernaryTree<T>, SnapshotError> {
+    let offset = self.pos;
+    match self.read_byte()? {
+      TAG_LEAF => Ok(Leaf(self.read_value(codec)?)),
+      TAG_CHUNK => {
+        let size = self.read_number()?;
+        if size == 0 || size > CHUNK_SIZE {
+          return Err(SnapshotError::InvalidChunk { offset, size });
+        }
+        let mut xs = Vec::with_capacity(size);
+        for _ in 0..size {
+          xs.push(self.read_value(codec)?);
+        }
+        Ok(Chunk(xs))
+      }
+      TAG_BRANCH2 => {
+        let left = self.read_id(nodes)?;
+        let middle = self.read_id(nodes)?;
+        Ok(Branch2 {
+          size: left.len().checked_add(middle.len()).ok_or(SnapshotError::SizeOverflow { offset })?,
+          left,
+          middle,
+          cache: NodeCache::new(),
+        })
+      }
+      TAG_BRANCH3 => {
+        let left = self.read_id(nodes)?;
+        let middle = self.read_id(nodes)?;
+        let right = self.read_id(nodes)?;
+        let size = left.len().checked_add(middle.len()).and_then(|size| size.checked_add(right.len()));
+        Ok(Branch3 {
+          size: size.ok_or(SnapshotError::SizeOverflow { offset })?,
+          left,
+          middle,
+          right,
+          cache: NodeCache::new(),
+        })
+      }
+      tag => Err(SnapshotError::InvalidTag { offset, tag }),
+    }
+  }
+}
+
+impl<T> TernaryTree<T> {
+  /// `None` for empty lists
+  pub(crate) fn write_snapshot<'a, I, C>(trees: I, codec: &C) -> Vec<u8>
+  where
+    T: 'a,
+    I: Iterator<Item = Option<&'a Self>>,
+    C: SnapshotCodec<T>,
+  {
+    let mut writer = SnapshotWriter {
+      codec,
+      ids: HashMap::new(),
+      count: 0,
+      body: vec![],
+      scratch: vec![],
+    };
+    // roots are not inside `Arc`s, each list gets its own root node
+    let roots = trees.map(|tree| tree.map(|t| writer.add_node(t))).collect::<Vec<_>>();
+
+    let mut out = Vec::with_capacity(writer.body.len() + roots.len() + 16);
+    out.extend_from_slice(MAGIC);
+    write_number(&mut out, writer.count);
+    out.extend_from_slice(&writer.body);
+    write_number(&mut out, roots.len());
+    for root in roots {
+      write_number(&mut out, root.map_or(0, |id| id + 1));
+    }
+    out
+  }
+}
+
+impl<T: Clone> TernaryTree<T> {
+  /// `None` for empty lists
+  pub(crate) fn read_snapshot<C: SnapshotCodec<T>>(bytes: &[u8], codec: &C) -> Result<Vec<Option<Self>>, SnapshotError> {
+    if bytes.get(..MAGIC.len()) != Some(MAGIC) {
+      return Err(SnapshotError::InvalidHeader);
+    }
+    let mut reader = SnapshotReader { bytes, pos: MAGIC.len() };
+
+    let count = reader.read_number()?;
+    let mut nodes: Vec<Arc<Self>> = Vec::with_capacity(count.min(MAX_PREALLOCATE));
+    for _ in 0..count {
+      let offset = reader.pos;
+      let node = reader.read_node(codec, &nodes)?;
+      // children are read before, so depth only looks at their cached depths
+      if node.depth() > MAX_READ_DEPTH {
+        return Err(SnapshotError::TooDeep { offset });
+      }
+      nodes.push(Arc::new(node));
+    }
+
+    let list_count = reader.read_number()?;
+    let mut trees = Vec::with_capacity(list_count.min(MAX_PREALLOCATE));
+    for _ in 0..list_count {
+      let offset = reader.pos;
+      match reader.read_number()? {
+        0 => trees.push(None),
+        n => {
+          let root = nodes.get(n - 1).ok_or(SnapshotError::InvalidReference { offset, id: n - 1 })?;
+          trees.push(Some(root.as_ref().to_owned()));
+        }
+      }
+    }
+
+    if reader.pos < bytes.len() {
+      return Err(SnapshotError::TrailingBytes { offset: reader.pos });
+    }
+    Ok(trees)
+  }
+}
@@ -0,0 +1,126 @@
+//! walks through trees to count nodes, depths and sharing.
+
+use super::TernaryTree::{self, *};
+
+use std::collections::HashMap;
+use std::mem::size_of;
+use std::sync::Arc;
+
+use crate::stats::{SharingStats, TreeStats};
+
+impl<T> TernaryTree<T> {
+  pub fn stats(&self) -> TreeStats {
+    let mut stats = TreeStats {
+      len: self.len(),
+      min_depth: usize::MAX,
+      // root node is held inline by the list, only its buffer takes heap
+      heap_bytes: self.buffer_bytes(),
+      ..TreeStats::default()
+    };
+    let mut depth_sum = 0;
+    self.walk_stats(0, &mut stats, &mut depth_sum);
+    stats.avg_depth = depth_sum as f64 / (stats.leaves + stats.chunks) as f64;
+    stats
+  }
+
+  fn walk_stats(&self, depth: usize, stats: &mut TreeStats, depth_sum: &mut usize) {
+    match self {
+      Leaf(_) => {
+        stats.leaves += 1;
+        record_depth(depth, stats, depth_sum);
+      }
+      Chunk(_) => {
+        stats.chunks += 1;
+        record_depth(depth, stats, depth_sum);
+      }
+      Branch2 { left, middle, .. } => {
+        stats.branch2 += 1;
+        for child in [left, middle] {
+          child.walk_child_stats(depth + 1, stats, depth_sum);
+        }
+      }
+      Branch3 { left, middle, right, .. } => {
+        stats.branch3 += 1;
+        for child in [left, middle, right] {
+          child.walk_child_stats(depth + 1, stats, depth_sum);
+        }
+      }
+    }
+  }
+
+  fn walk_child_stats(self: &Arc<Self>, depth: usize, stats: &mut TreeStats, depth_sum: &mut usize) {
+    if Arc::strong_count(self) > 1 {
+      stats.shared_nodes += 1;
+    }
+    stats.heap_bytes += arc_node_bytes(self);
+    self.walk_stats(depth, stats, depth_sum);
+  }
+
+  /// heap taken by elements of a chunk
+  fn buffer_bytes(&self) -> usize {
+    match self {
+      Chunk(xs) => xs.capacity() * size_of::<T>(),
+      _ => 0,
+    }
+  }
+
+  pub(crate) fn sharing_stats<'a>(trees: impl Iterator<Item = &'a Self>) -> SharingStats
+  where
+    T: 'a,
+  {
+    let mut stats = SharingStats::default();
+    // nodes and bytes inside each visited `Arc`, so shared subtrees are only walked once
+    let mut seen: HashMap<*const Self, (usize, usize)> = HashMap::new();
+    for tree in trees {
+      stats.lists += 1;
+      let (nodes, bytes) = tree.walk_sharing(&mut seen, &mut stats);
+      let own_bytes = tree.buffer_bytes();
+      stats.total_nodes += nodes + 1;
+      stats.unique_nodes += 1;
+      stats.total_heap_bytes += bytes + own_bytes;
+      stats.unique_heap_bytes += own_bytes;
+    }
+    stats
+  }
+
+  /// returns nodes and bytes of children as if nothing is shared
+  fn walk_sharing(&self, seen: &mut HashMap<*const Self, (usize, usize)>, stats: &mut SharingStats) -> (usize, usize) {
+    let children: &[&Arc<Self>] = match self {
+      Leaf(_) | Chunk(_) => &[],
+      Branch2 { left, middle, .. } => &[left, middle],
+      Branch3 { left, middle, right, .. } => &[left, middle, right],
+    };
+    let mut acc = (0, 0);
+    for child in children {
+      let (nodes, bytes) = match seen.get(&Arc::as_ptr(child)) {
+        Some(found) => *found,
+        None => {
+          let own_bytes = arc_node_bytes(child);
+          stats.unique_nodes += 1;
+          stats.unique_heap_bytes += own_bytes;
+          if Arc::strong_count(child) > 1 {
+            stats.arc_shared_nodes += 1;
+          }
+          let (nodes, bytes) = child.walk_sharing(seen, stats);
+          let found = (nodes + 1, bytes + own_bytes);
+          seen.insert(Arc::as_ptr(child), found);
+          found
+        }
+      };
+      acc.0 += nodes;
+      acc.1 += bytes;
+    }
+    acc
+  }
+}
+
+fn record_depth(depth: usize, stats: &mut TreeStats, depth_sum: &mut usize) {
+  stats.min_depth = stats.min_depth.min(depth);
+  stats.max_depth = stats.max_depth.max(depth);
+  *depth_sum += depth;
+}
+
+/// node allocated by `Arc`, with its counters, and buffer of a chunk
+fn arc_node_bytes<T>(node: &Arc<TernaryTree<T>>) -> usize {
+  2 * size_of::<usize>() + size_of::<TernaryTree<T>>() + node.buffer_bytes()
+}
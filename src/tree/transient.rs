@@ -0,0 +1,519 @@
+//! in-place operations for transient editing.
+//! branches are reached through `Arc::make_mut`, so nodes owned by only one tree are changed without copying,
+//! and nodes shared with other versions are copied once and then owned.
+//! structures follow the persistent versions in `finger.rs`, a list ends up in same shape either way.
+//!
+//! an empty chunk is used as a placeholder when a node is moved out temporarily, it never stays in a tree.
+
+use super::TernaryTree::{self, *};
+use super::{CHUNK_SIZE, NodeCache};
+
+use std::sync::Arc;
+
+use crate::util::triple_size;
+
+impl<T: Clone> TernaryTree<T> {
+  /// move node out, leaving a placeholder
+  fn take_node(&mut self) -> Self {
+    std::mem::replace(self, Chunk(vec![]))
+  }
+
+  /// take the only element from a leaf or a chunk of size 1
+  pub(crate) fn into_single(self) -> T {
+    match self {
+      Leaf(value) => value,
+      Chunk(mut xs) if xs.len() == 1 => xs.pop().expect("chunk of size 1"),
+      _ => unreachable!("expected a node of single element"),
+    }
+  }
+
+  /// wrap current node into a branch together with `item`
+  fn wrap_mut(&mut self, item: Self, item_at_left: bool) {
+    let size = self.len() + item.len();
+    let old = self.take_node();
+    let (left, middle) = if item_at_left { (item, old) } else { (old, item) };
+    *self = Branch2 {
+      size,
+      left: Arc::new(left),
+      middle: Arc::new(middle),
+      cache: NodeCache::new(),
+    };
+  }
+
+  pub fn assoc_mut(&mut self, idx: usize, item: T) {
+    match self {
+      Leaf(value) => *value = item,
+      Chunk(xs) => xs[idx] = item,
+      Branch2 { left, middle, cache, .. } => {
+        *cache = NodeCache::new();
+        if idx < left.len() {
+          Arc::make_mut(left).assoc_mut(idx, item)
+        } else {
+          let base = left.len();
+          Arc::make_mut(middle).assoc_mut(idx - base, item)
+        }
+      }
+      Branch3 {
+        left,
+        middle,
+        right,
+        cache,
+        ..
+      } => {
+        *cache = NodeCache::new();
+        let base1 = left.len();
+        let base2 = base1 + middle.len();
+        if idx < base1 {
+          Arc::make_mut(left).assoc_mut(idx, item)
+        } else if idx < base2 {
+          Arc::make_mut(middle).assoc_mut(idx - base1, item)
+        } else {
+          Arc::make_mut(right).assoc_mut(idx - base2, item)
+        }
+      }
+    }
+  }
+
+  pub fn push_right_mut(&mut self, item: T) {
+    match self.last_leaf() {
+      Chunk(xs) if xs.len() < CHUNK_SIZE => self.push_right_chunk_mut(item),
+      Chunk(_) => self.push_right_main_mut(Chunk(vec![item]), 2, CHUNK_SIZE),
+      _ => self.push_right_main_mut(Leaf(item), 2, 1),
+    }
+  }
+
+  pub fn push_left_mut(&mut self, item: T) {
+    match self.first_leaf() {
+      Chunk(xs) if xs.len() < CHUNK_SIZE => self.push_left_chunk_mut(item),
+      Chunk(_) => self.push_left_main_mut(Chunk(vec![item]), 2, CHUNK_SIZE),
+      _ => self.push_left_main_mut(Leaf(item), 2, 1),
+    }
+  }
+
+  fn push_right_chunk_mut(&mut self, item: T) {
+    match self {
+      Leaf(_) => unreachable!("expected a chunk at right edge"),
+      Chunk(xs) => xs.push(item),
+      Branch2 { size, middle, cache, .. } => {
+        *size += 1;
+        *cache = NodeCache::new();
+        Arc::make_mut(middle).push_right_chunk_mut(item)
+      }
+      Branch3 { size, right, cache, .. } => {
+        *size += 1;
+        *cache = NodeCache::new();
+        Arc::make_mut(right).push_right_chunk_mut(item)
+      }
+    }
+  }
+
+  fn push_left_chunk_mut(&mut self, item: T) {
+    match self {
+      Leaf(_) => unreachable!("expected a chunk at left edge"),
+      Chunk(xs) => xs.insert(0, item),
+      Branch2 { size, left, cache, .. } | Branch3 { size, left, cache, .. } => {
+        *size += 1;
+        *cache = NodeCache::new();
+        Arc::make_mut(left).push_left_chunk_mut(item)
+      }
+    }
+  }
+
+  /// same as `push_right_main`
+  fn push_right_main_mut(&mut self, item: Self, n: u8, unit: usize) {
+    let weight = item.len().max(unit);
+    if self.len() + weight <= triple_size(n) * unit {
+      return self.push_right_side_mut(item, unit);
+    }
+    match self {
+      Leaf(_) | Chunk(_) => self.push_right_side_mut(item, unit),
+      Branch2 { size, left, middle, cache } => {
+        if middle.len() + weight > triple_size(n) * unit {
+          *self = Branch3 {
+            size: *size + item.len(),
+            left: left.to_owned(),
+            middle: middle.to_owned(),
+            right: Arc::new(item),
+            cache: NodeCache::new(),
+          };
+        } else {
+          *size += item.len();
+          *cache = NodeCache::new();
+          Arc::make_mut(middle).push_right_side_mut(item, unit);
+        }
+      }
+      Branch3 {
+        size,
+        left,
+        middle,
+        right,
+        cache,
+      } => {
+        *cache = NodeCache::new();
+        if right.len() + weight > triple_size(n - 1) * unit {
+          let prev_right = take_child(right);
+          *right = Arc::new(item);
+          Arc::make_mut(middle).push_right_main_mut(prev_right, n + 1, unit);
+          *size = left.len() + middle.len() + right.len();
+        } else {
+          *size += item.len();
+          Arc::make_mut(right).push_right_side_mut(item, unit);
+        }
+      }
+    }
+  }
+
+  /// same as `push_left_main`
+  fn push_left_main_mut(&mut self, item: Self, n: u8, unit: usize) {
+    let weight = item.len().max(unit);
+    if self.len() + weight <= triple_size(n) * unit {
+      return self.push_left_side_mut(item, unit);
+    }
+    match self {
+      Leaf(_) | Chunk(_) => self.push_left_side_mut(item, unit),
+      Branch2 { size, left, middle, cache } => {
+        if left.len() + weight > triple_size(n) * unit {
+          *self = Branch3 {
+            size: *size + item.len(),
+            left: Arc::new(item),
+            middle: left.to_owned(),
+            right: middle.to_owned(),
+            cache: NodeCache::new(),
+          };
+        } else {
+          *size += item.len();
+          *cache = NodeCache::new();
+          Arc::make_mut(left).push_left_side_mut(item, unit);
+        }
+      }
+      Branch3 {
+        size,
+        left,
+        middle,
+        right,
+        cache,
+      } => {
+        *cache = NodeCache::new();
+        if left.len() + weight > triple_size(n - 1) * unit {
+          let prev_left = take_child(left);
+          *left = Arc::new(item);
+          Arc::make_mut(middle).push_left_main_mut(prev_left, n + 1, unit);
+          *size = left.len() + middle.len() + right.len();
+        } else {
+          *size += item.len();
+          Arc::make_mut(left).push_left_side_mut(item, unit);
+        }
+      }
+    }
+  }
+
+  /// same as `push_right_side`
+  fn push_right_side_mut(&mut self, item: Self, unit: usize) {
+    let weight = item.len().max(unit);
+    match self {
+      Leaf(_) | Chunk(_) => self.wrap_mut(item, false),
+      Branch2 { size, left, middle, cache } => {
+        if middle.len() + weight > left.len() {
+          *self = Branch3 {
+            size: *size + item.len(),
+            left: left.to_owned(),
+            middle: middle.to_owned(),
+            right: Arc::new(item),
+            cache: NodeCache::new(),
+          };
+        } else {
+          *size += item.len();
+          *cache = NodeCache::new();
+          Arc::make_mut(middle).push_right_side_mut(item, unit);
+        }
+      }
+      Branch3 {
+        size,
+        middle,
+        right,
+        cache,
+        ..
+      } => {
+        if right.len() + weight > middle.len() {
+          self.wrap_mut(item, false);
+        } else {
+          *size += item.len();
+          *cache = NodeCache::new();
+          Arc::make_mut(right).push_right_side_mut(item, unit);
+        }
+      }
+    }
+  }
+
+  /// same as `push_left_side`
+  fn push_left_side_mut(&mut self, item: Self, unit: usize) {
+    let weight = item.len().max(unit);
+    match self {
+      Leaf(_) | Chunk(_) => self.wrap_mut(item, true),
+      Branch2 { size, left, middle, cache } => {
+        if left.len() + weight > middle.len() {
+          *self = Branch3 {
+            size: *size + item.len(),
+            left: Arc::new(item),
+            middle: left.to_owned(),
+            right: middle.to_owned(),
+            cache: NodeCache::new(),
+          };
+        } else {
+          *size += item.len();
+          *cache = NodeCache::new();
+          Arc::make_mut(left).push_left_side_mut(item, unit);
+        }
+      }
+      Branch3 {
+        size, left, middle, cache, ..
+      } => {
+        if left.len() + weight > middle.len() {
+          self.wrap_mut(item, true);
+        } else {
+          *size += item.len();
+          *cache = NodeCache::new();
+          Arc::make_mut(left).push_left_side_mut(item, unit);
+        }
+      }
+    }
+  }
+
+  /// remove first element and return it, same as `drop_left`. tree should have more than 1 element
+  pub fn pop_left_mut(&mut self) -> T {
+    match self {
+      Leaf(_) => unreachable!("not expected empty node inside tree"),
+      Chunk(xs) => xs.remove(0),
+      Branch2 { size, left, middle, cache } => {
+        if left.len() == 1 {
+          let value = take_child(left).into_single();
+          *self = take_child(middle);
+          return value;
+        }
+        let value = Arc::make_mut(left).pop_left_mut();
+        match &**left {
+          Branch2 { .. } | Branch3 { .. } => {
+            let size = *size - 1;
+            let middle = middle.to_owned();
+            *self = match take_child(left) {
+              Branch2 {
+                left: b_left,
+                middle: b_middle,
+                ..
+              } => Branch3 {
+                size,
+                left: b_left,
+                middle: b_middle,
+                right: middle,
+                cache: NodeCache::new(),
+              },
+              Branch3 {
+                left: b_left,
+                middle: b_middle,
+                right: b_right,
+                ..
+              } => Branch3 {
+                size,
+                left: b_left,
+                middle: Arc::new(Branch2 {
+                  size: b_middle.len() + b_right.len(),
+                  left: b_middle,
+                  middle: b_right,
+                  cache: NodeCache::new(),
+                }),
+                right: middle,
+                cache: NodeCache::new(),
+              },
+              _ => unreachable!("checked as a branch"),
+            };
+          }
+          _ => {
+            *size -= 1;
+            *cache = NodeCache::new();
+          }
+        }
+        value
+      }
+      Branch3 {
+        size,
+        left,
+        middle,
+        right,
+        cache,
+      } => {
+        if left.len() == 1 {
+          let value = take_child(left).into_single();
+          let size = *size - 1;
+          let right = right.to_owned();
+          *self = match &**middle {
+            Branch2 { .. } | Branch3 { .. } => match take_child(middle) {
+              Branch2 {
+                left: b_left,
+                middle: b_middle,
+                ..
+              } => Branch3 {
+                size,
+                left: b_left,
+                middle: b_middle,
+                right,
+                cache: NodeCache::new(),
+              },
+              Branch3 {
+                left: b_left,
+                middle: b_middle,
+                right: b_right,
+                ..
+              } => Branch3 {
+                size,
+                left: b_left,
+                middle: Arc::new(Branch2 {
+                  size: b_middle.len() + b_right.len(),
+                  left: b_middle,
+                  middle: b_right,
+                  cache: NodeCache::new(),
+                }),
+                right,
+                cache: NodeCache::new(),
+              },
+              _ => unreachable!("checked as a branch"),
+            },
+            _ => Branch2 {
+              size,
+              left: middle.to_owned(),
+              middle: right,
+              cache: NodeCache::new(),
+            },
+          };
+          value
+        } else {
+          *size -= 1;
+          *cache = NodeCache::new();
+          Arc::make_mut(left).pop_left_mut()
+        }
+      }
+    }
+  }
+
+  /// remove last element and return it, same as `drop_right`. tree should have more than 1 element
+  pub fn pop_right_mut(&mut self) -> T {
+    match self {
+      Leaf(_) => unreachable!("not expected empty node inside tree"),
+      Chunk(xs) => xs.pop().expect("chunk is never empty"),
+      Branch2 { size, left, middle, cache } => {
+        if middle.len() == 1 {
+          let value = take_child(middle).into_single();
+          *self = take_child(left);
+          return value;
+        }
+        let value = Arc::make_mut(middle).pop_right_mut();
+        match &**middle {
+          Branch2 { .. } | Branch3 { .. } => {
+            let size = *size - 1;
+            let left = left.to_owned();
+            *self = match take_child(middle) {
+              Branch2 {
+                left: b_left,
+                middle: b_middle,
+                ..
+              } => Branch3 {
+                size,
+                left,
+                middle: b_left,
+                right: b_middle,
+                cache: NodeCache::new(),
+              },
+              Branch3 {
+                left: b_left,
+                middle: b_middle,
+                right: b_right,
+                ..
+              } => Branch3 {
+                size,
+                left,
+                middle: Arc::new(Branch2 {
+                  size: b_left.len() + b_middle.len(),
+                  left: b_left,
+                  middle: b_middle,
+                  cache: NodeCache::new(),
+                }),
+                right: b_right,
+                cache: NodeCache::new(),
+              },
+              _ => unreachable!("checked as a branch"),
+            };
+          }
+          _ => {
+            *size -= 1;
+            *cache = NodeCache::new();
+          }
+        }
+        value
+      }
+      Branch3 {
+        size,
+        left,
+        middle,
+        right,
+        cache,
+      } => {
+        if right.len() == 1 {
+          let value = take_child(right).into_single();
+          let size = *size - 1;
+          let left = left.to_owned();
+          *self = match &**middle {
+            Branch2 { .. } | Branch3 { .. } => match take_child(middle) {
+              Branch2 {
+                left: b_left,
+                middle: b_middle,
+                ..
+              } => Branch3 {
+                size,
+                left,
+                middle: b_left,
+                right: b_middle,
+                cache: NodeCache::new(),
+              },
+              Branch3 {
+                left: b_left,
+                middle: b_middle,
+                right: b_right,
+                ..
+              } => Branch3 {
+                size,
+                left,
+                middle: Arc::new(Branch2 {
+                  size: b_left.len() + b_middle.len(),
+                  left: b_left,
+                  middle: b_middle,
+                  cache: NodeCache::new(),
+                }),
+                right: b_right,
+                cache: NodeCache::new(),
+              },
+              _ => unreachable!("checked as a branch"),
+            },
+            _ => Branch2 {
+              size,
+              left,
+              middle: middle.to_owned(),
+              cache: NodeCache::new(),
+            },
+          };
+          value
+        } else {
+          *size -= 1;
+          *cache = NodeCache::new();
+          Arc::make_mut(right).pop_right_mut()
+        }
+      }
+    }
+  }
+}
+
+/// move node out of a child, the node is copied when it's shared with other trees
+fn take_child<T: Clone>(child: &mut Arc<TernaryTree<T>>) -> TernaryTree<T> {
+  match Arc::get_mut(child) {
+    Some(node) => node.take_node(),
+    None => (**child).to_owned(),
+  }
+}
@@ -1,3 +1,7 @@
+use std::ops::{Bound, RangeBounds};
+
+use crate::error::TernaryTreeError;
+
 pub(crate) fn divide_ternary_sizes(size: usize) -> (usize, usize, usize) {
   let group_size = size / 3;
   let extra = size - group_size * 3;
@@ -9,3 +13,27 @@ pub(crate) fn triple_size(t: u8) -> usize {
   let n: usize = 3;
   n.pow(t as u32)
 }
+
+/// deepest path allowed in an edited branch of this size, around 3 times the depth of a balanced tree
+pub(crate) fn depth_limit(size: usize) -> usize {
+  2 * (usize::BITS - size.leading_zeros()) as usize
+}
+
+/// turn range bounds into `start..end` and check it's inside a list of `len`
+pub(crate) fn resolve_range<R: RangeBounds<usize>>(range: &R, len: usize) -> Result<(usize, usize), TernaryTreeError> {
+  let start = match range.start_bound() {
+    Bound::Included(i) => *i,
+    Bound::Excluded(i) => i.saturating_add(1),
+    Bound::Unbounded => 0,
+  };
+  let end = match range.end_bound() {
+    Bound::Included(i) => i.saturating_add(1),
+    Bound::Excluded(i) => *i,
+    Bound::Unbounded => len,
+  };
+  if start > end || end > len {
+    Err(TernaryTreeError::InvalidRange { start, end })
+  } else {
+    Ok((start, end))
+  }
+}
@@ -0,0 +1,27 @@
+//! helpers shared by test files, not every file uses all of them
+#![allow(dead_code)]
+
+use im_ternary_tree::SnapshotCodec;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+pub fn hash_of<X: Hash + ?Sized>(x: &X) -> u64 {
+  let mut hasher = DefaultHasher::new();
+  x.hash(&mut hasher);
+  hasher.finish()
+}
+
+/// writes each byte as it is
+pub struct ByteCodec;
+
+impl SnapshotCodec<u8> for ByteCodec {
+  fn encode(&self, item: &u8, out: &mut Vec<u8>) {
+    out.push(*item);
+  }
+
+  fn decode(&self, bytes: &[u8]) -> Option<u8> {
+    match bytes {
+      [x] => Some(*x),
+      _ => None,
+    }
+  }
+}
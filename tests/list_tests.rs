@@ -1,10 +1,17 @@
 extern crate im_ternary_tree;
 
-use im_ternary_tree::TernaryTreeList;
+mod common;
+
+use common::hash_of;
+use im_ternary_tree::{
+  CHUNK_SIZE, FormatTreeOptions, ParseInlineError, SnapshotCodec, SnapshotError, StructureIssue, TernaryTreeError, TernaryTreeList,
+  TernaryTreeListBuilder, TreeStats,
+};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 
 #[test]
-fn init_list() -> Result<(), String> {
+fn init_list() -> Result<(), TernaryTreeError> {
   assert_eq!(
     TernaryTreeList::from(&[1, 2, 3, 4]).to_string(),
     String::from("(TernaryTree 1 2 3 4)")
@@ -29,7 +36,7 @@ fn init_list() -> Result<(), String> {
 }
 
 #[test]
-fn init_list_push_right() -> Result<(), String> {
+fn init_list_push_right() -> Result<(), TernaryTreeError> {
   let mut data: Vec<usize> = vec![];
   let mut tree: TernaryTreeList<usize> = TernaryTreeList::Empty;
   for idx in 1..200 {
@@ -44,7 +51,7 @@ fn init_list_push_right() -> Result<(), String> {
 }
 
 #[test]
-fn init_list_push_left() -> Result<(), String> {
+fn init_list_push_left() -> Result<(), TernaryTreeError> {
   let mut data: Vec<usize> = vec![];
   let mut tree: TernaryTreeList<usize> = TernaryTreeList::Empty;
   for idx in 1..200 {
@@ -59,7 +66,7 @@ fn init_list_push_left() -> Result<(), String> {
 }
 
 #[test]
-fn list_operations() -> Result<(), String> {
+fn list_operations() -> Result<(), TernaryTreeError> {
   let origin11 = &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
   let data11 = TernaryTreeList::from(origin11);
 
@@ -107,7 +114,7 @@ fn list_operations() -> Result<(), String> {
 }
 
 #[test]
-fn drop_left_data() -> Result<(), String> {
+fn drop_left_data() -> Result<(), TernaryTreeError> {
   let mut data: Vec<usize> = vec![];
   for idx in 0..200 {
     data.push(idx);
@@ -126,7 +133,7 @@ fn drop_left_data() -> Result<(), String> {
 }
 
 #[test]
-fn drop_right_data() -> Result<(), String> {
+fn drop_right_data() -> Result<(), TernaryTreeError> {
   let mut data: Vec<usize> = vec![];
   for idx in 0..200 {
     data.push(idx);
@@ -145,7 +152,7 @@ fn drop_right_data() -> Result<(), String> {
 }
 
 #[test]
-fn drop_left_shallow() -> Result<(), String> {
+fn drop_left_shallow() -> Result<(), TernaryTreeError> {
   let mut data: Vec<usize> = vec![];
   for idx in 0..200 {
     data.push(idx);
@@ -164,7 +171,7 @@ fn drop_left_shallow() -> Result<(), String> {
 }
 
 #[test]
-fn drop_right_shallow() -> Result<(), String> {
+fn drop_right_shallow() -> Result<(), TernaryTreeError> {
   let mut data: Vec<usize> = vec![];
   for idx in 0..200 {
     data.push(idx);
@@ -183,7 +190,7 @@ fn drop_right_shallow() -> Result<(), String> {
 }
 
 #[test]
-fn dissoc() -> Result<(), String> {
+fn dissoc() -> Result<(), TernaryTreeError> {
   let data = TernaryTreeList::from(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
   assert_eq!(data.format_inline(), "((0 1 2) (3 (4 5) 6) (7 8 9))");
   assert_eq!(data.dissoc(4)?.format_inline(), "((0 1 2) (3 5 6) (7 8 9))");
@@ -192,7 +199,7 @@ fn dissoc() -> Result<(), String> {
 }
 
 #[test]
-fn list_insertions() -> Result<(), String> {
+fn list_insertions() -> Result<(), TernaryTreeError> {
   let origin5 = [1, 2, 3, 4, 5];
   let data5 = TernaryTreeList::from(&origin5);
 
@@ -222,7 +229,7 @@ fn list_insertions() -> Result<(), String> {
 }
 
 #[test]
-fn test_concat() -> Result<(), String> {
+fn test_concat() -> Result<(), TernaryTreeError> {
   let data1 = TernaryTreeList::from(&[1, 2]);
   let data2 = TernaryTreeList::from(&[3, 4]);
 
@@ -256,7 +263,7 @@ fn test_concat() -> Result<(), String> {
 }
 
 #[test]
-fn check_equality() -> Result<(), String> {
+fn check_equality() -> Result<(), TernaryTreeError> {
   let origin4 = [1, 2, 3, 4];
   let data4 = TernaryTreeList::from(&origin4);
   let data4n = TernaryTreeList::from(&origin4);
@@ -275,7 +282,7 @@ fn check_equality() -> Result<(), String> {
 }
 
 #[test]
-fn force_balancing() -> Result<(), String> {
+fn force_balancing() -> Result<(), TernaryTreeError> {
   let mut data = TernaryTreeList::<usize>::from(&[]);
   for idx in 0..20 {
     data = data.append(idx);
@@ -298,7 +305,7 @@ fn force_balancing() -> Result<(), String> {
 }
 
 #[test]
-fn iterator() -> Result<(), String> {
+fn iterator() -> Result<(), TernaryTreeError> {
   let origin4 = [1, 2, 3, 4];
   let data4 = TernaryTreeList::from(&origin4);
 
@@ -320,7 +327,8 @@ fn iterator() -> Result<(), String> {
 }
 
 #[test]
-fn iter_enum() -> Result<(), String> {
+#[allow(unused_assignments)]
+fn iter_enum() -> Result<(), TernaryTreeError> {
   let origin4 = [1, 2, 3, 4];
   let data4 = TernaryTreeList::from(&origin4);
 
@@ -341,7 +349,7 @@ fn iter_enum() -> Result<(), String> {
 }
 
 #[test]
-fn check_structure() -> Result<(), String> {
+fn check_structure() -> Result<(), TernaryTreeError> {
   let mut data = TernaryTreeList::from(&[]);
   for idx in 0..20 {
     data = data.append(idx);
@@ -358,7 +366,7 @@ fn check_structure() -> Result<(), String> {
 }
 
 #[test]
-fn slices() -> Result<(), String> {
+fn slices() -> Result<(), TernaryTreeError> {
   let mut data = TernaryTreeList::from(&[]);
   for idx in 0..40 {
     data = data.append(idx);
@@ -378,7 +386,7 @@ fn slices() -> Result<(), String> {
 }
 
 #[test]
-fn reverse() -> Result<(), String> {
+fn reverse() -> Result<(), TernaryTreeError> {
   let data = TernaryTreeList::from(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
   let reversed_data = data.reverse();
   let mut d2 = data.to_vec();
@@ -390,7 +398,8 @@ fn reverse() -> Result<(), String> {
 }
 
 #[test]
-fn list_traverse() -> Result<(), String> {
+#[allow(unused_assignments)]
+fn list_traverse() -> Result<(), TernaryTreeError> {
   let mut i = 0;
   let data = TernaryTreeList::from(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
   for _ in &data {
@@ -413,7 +422,20 @@ fn list_traverse() -> Result<(), String> {
 }
 
 #[test]
-fn index_of() -> Result<(), String> {
+fn iter_exhausted() {
+  let data = TernaryTreeList::from(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+  assert_eq!(data.iter().count(), 10);
+  assert_eq!(data.iter().enumerate().map(|(idx, _)| idx).sum::<usize>(), 45);
+
+  let single = TernaryTreeList::from(&[1]);
+  let mut xs = single.iter();
+  assert_eq!(xs.next(), Some(&1));
+  assert_eq!(xs.next(), None);
+  assert_eq!(xs.next(), None);
+}
+
+#[test]
+fn index_of() -> Result<(), TernaryTreeError> {
   let data = TernaryTreeList::from(&[1, 2, 3, 4, 5, 6, 7, 8]);
   assert_eq!(data.index_of(&2), Some(1));
   assert_eq!(data.find_index(Arc::new(|x| -> bool { x == &2 })), Some(1));
@@ -424,7 +446,7 @@ fn index_of() -> Result<(), String> {
 }
 
 #[test]
-fn map_values() -> Result<(), String> {
+fn map_values() -> Result<(), TernaryTreeError> {
   let data = TernaryTreeList::from(&[1, 2, 3, 4]);
   let data2 = TernaryTreeList::from(&[1, 4, 9, 16]);
   let data3 = data.map(Arc::new(|x| x * x));
@@ -437,7 +459,7 @@ fn map_values() -> Result<(), String> {
 }
 
 #[test]
-fn index_elem() -> Result<(), String> {
+fn index_elem() -> Result<(), TernaryTreeError> {
   let data = TernaryTreeList::from(&[1, 2, 3, 4, 5, 6, 7, 8]);
 
   assert_eq!(data[0], 1);
@@ -447,7 +469,7 @@ fn index_elem() -> Result<(), String> {
 }
 
 #[test]
-fn take_skip() -> Result<(), String> {
+fn take_skip() -> Result<(), TernaryTreeError> {
   let data = TernaryTreeList::from(&[1, 2, 3, 4, 5, 6, 7, 8]);
 
   assert_eq!(data.skip(2).unwrap(), TernaryTreeList::from(&[3, 4, 5, 6, 7, 8]));
@@ -457,7 +479,7 @@ fn take_skip() -> Result<(), String> {
 }
 
 #[test]
-fn dissoc_empty() -> Result<(), String> {
+fn dissoc_empty() -> Result<(), TernaryTreeError> {
   let data = TernaryTreeList::from(&[1]);
 
   assert_eq!(data.dissoc(0).unwrap(), TernaryTreeList::Empty);
@@ -466,7 +488,7 @@ fn dissoc_empty() -> Result<(), String> {
 }
 
 #[test]
-fn split_values() -> Result<(), String> {
+fn split_values() -> Result<(), TernaryTreeError> {
   let n = 100;
   let mut data = TernaryTreeList::from(&[]);
   for idx in 0..n {
@@ -485,7 +507,7 @@ fn split_values() -> Result<(), String> {
 }
 
 #[test]
-fn traverse() -> Result<(), String> {
+fn traverse() -> Result<(), TernaryTreeError> {
   let n = 100;
   let mut data = TernaryTreeList::from(&[]);
   let mut total = 0;
@@ -526,3 +548,1073 @@ fn traverse_result() -> Result<(), String> {
 
   Ok(())
 }
+
+#[test]
+fn relaxed_bounds() -> Result<(), TernaryTreeError> {
+  let floats = TernaryTreeList::from(&[1.5, 2.5, f64::NAN]).push_right(4.0);
+  assert_eq!(floats.len(), 4);
+  assert_eq!(floats.get(1), Some(&2.5));
+  assert_eq!(floats.format_inline(), "((1.5 2.5 NaN) 4)");
+  // NaN is not equal to itself, even inside subtrees shared by both lists
+  assert!(floats != floats.clone());
+  assert_eq!(floats.partial_cmp(&floats.clone()), None);
+  assert!(floats != TernaryTreeList::from(&[1.5, 2.5, f64::NAN, 4.0]));
+  assert!(TernaryTreeList::from(&[1.0, 2.0]) < TernaryTreeList::from(&[1.0, 3.0]));
+
+  type Op = Arc<dyn Fn(usize) -> usize>;
+  let inc: Op = Arc::new(|x| x + 1);
+  let double: Op = Arc::new(|x| x * 2);
+  let fs = TernaryTreeList::from(vec![inc, double]);
+  let updated = fs.assoc(0, Arc::new(|x| x + 10))?;
+  assert_eq!(updated.iter().map(|f| f(1)).collect::<Vec<_>>(), vec![11, 2]);
+  updated.check_structure()?;
+
+  Ok(())
+}
+
+#[test]
+fn typed_errors() -> Result<(), TernaryTreeError> {
+  let data = TernaryTreeList::from(&[1, 2, 3, 4]);
+  let empty = TernaryTreeList::<usize>::Empty;
+
+  assert_eq!(data.assoc(4, 10), Err(TernaryTreeError::IndexOutOfBounds { index: 4, len: 4 }));
+  assert_eq!(data.dissoc(5), Err(TernaryTreeError::IndexOutOfBounds { index: 5, len: 4 }));
+  assert_eq!(empty.assoc(0, 1), Err(TernaryTreeError::EmptyList));
+  assert_eq!(empty.rest(), Err(TernaryTreeError::EmptyList));
+  assert_eq!(empty.butlast(), Err(TernaryTreeError::EmptyList));
+  assert_eq!(
+    empty.insert(1, 1, false),
+    Err(TernaryTreeError::IndexOutOfBounds { index: 1, len: 0 })
+  );
+  assert_eq!(
+    TernaryTreeList::from(&[1, 2, 3, 4, 5]).insert(100, 9, false),
+    Err(TernaryTreeError::IndexOutOfBounds { index: 100, len: 5 })
+  );
+  assert_eq!(
+    data.insert(4, 9, true),
+    Err(TernaryTreeError::IndexOutOfBounds { index: 4, len: 4 })
+  );
+  // chunked lists report the same errors
+  let chunked = TernaryTreeList::from_chunked([1, 2, 3, 4, 5]);
+  assert_eq!(
+    chunked.insert(5, 9, true),
+    Err(TernaryTreeError::IndexOutOfBounds { index: 5, len: 5 })
+  );
+  assert_eq!(
+    chunked.insert(100, 9, false),
+    Err(TernaryTreeError::IndexOutOfBounds { index: 100, len: 5 })
+  );
+  assert_eq!(chunked.insert(4, 9, true)?.to_vec(), vec![1, 2, 3, 4, 5, 9]);
+  assert_eq!(data.slice(3, 2), Err(TernaryTreeError::InvalidRange { start: 3, end: 2 }));
+  assert_eq!(data.slice(1, 5), Err(TernaryTreeError::InvalidRange { start: 1, end: 5 }));
+  assert_eq!(data.take(5), Err(TernaryTreeError::InvalidRange { start: 0, end: 5 }));
+  assert_eq!(data.skip(5), Err(TernaryTreeError::InvalidRange { start: 5, end: 4 }));
+
+  assert_eq!(
+    data.assoc(9, 10).unwrap_err().to_string(),
+    "index 9 is out of bound for list of size 4"
+  );
+  assert_eq!(data.slice(1, 3)?, TernaryTreeList::from(&[2, 3]));
+
+  Ok(())
+}
+
+#[test]
+fn iterator_exact_size() -> Result<(), TernaryTreeError> {
+  let mut data = TernaryTreeList::Empty;
+  for idx in 0..100 {
+    data = data.push_right(idx);
+  }
+
+  let mut it = data.iter();
+  assert_eq!(it.len(), 100);
+  assert_eq!(it.next(), Some(&0));
+  assert_eq!(it.size_hint(), (99, Some(99)));
+  assert_eq!(it.by_ref().count(), 99);
+  assert_eq!(it.next(), None);
+  assert_eq!(it.next(), None);
+
+  assert_eq!(data.iter().copied().collect::<Vec<_>>(), (0..100).collect::<Vec<_>>());
+  assert_eq!(TernaryTreeList::<usize>::Empty.iter().len(), 0);
+
+  Ok(())
+}
+
+#[test]
+fn iterator_double_ended() -> Result<(), TernaryTreeError> {
+  let data = TernaryTreeList::from(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]);
+
+  assert_eq!(data.iter().rev().copied().collect::<Vec<_>>(), data.reverse().to_vec());
+  assert_eq!(data.iter().rfind(|x| **x % 3 == 0), Some(&9));
+
+  let mut it = data.iter();
+  assert_eq!(it.next(), Some(&1));
+  assert_eq!(it.next_back(), Some(&11));
+  assert_eq!(it.next_back(), Some(&10));
+  assert_eq!(it.len(), 8);
+  assert_eq!(it.next(), Some(&2));
+  let rest: Vec<_> = it.by_ref().copied().collect();
+  assert_eq!(rest, vec![3, 4, 5, 6, 7, 8, 9]);
+  assert_eq!(it.next_back(), None);
+  assert_eq!(it.next(), None);
+
+  Ok(())
+}
+
+#[derive(Debug)]
+struct CloneCounted(usize, Arc<AtomicUsize>);
+
+impl Clone for CloneCounted {
+  fn clone(&self) -> Self {
+    self.1.fetch_add(1, AtomicOrdering::SeqCst);
+    CloneCounted(self.0, self.1.clone())
+  }
+}
+
+#[test]
+fn owning_iterator() -> Result<(), TernaryTreeError> {
+  let counter = Arc::new(AtomicUsize::new(0));
+  let mut data = TernaryTreeList::Empty;
+  for idx in 0..50 {
+    data = data.push_right(CloneCounted(idx, counter.clone()));
+  }
+
+  let shared = data.clone();
+  counter.store(0, AtomicOrdering::SeqCst);
+  let values: Vec<usize> = data.into_iter().map(|x| x.0).collect();
+  assert_eq!(values, (0..50).collect::<Vec<_>>());
+  assert_eq!(counter.load(AtomicOrdering::SeqCst), 50, "shared nodes are cloned");
+
+  counter.store(0, AtomicOrdering::SeqCst);
+  let mut it = shared.into_iter();
+  assert_eq!(it.len(), 50);
+  assert_eq!(it.next_back().map(|x| x.0), Some(49));
+  assert_eq!(it.next().map(|x| x.0), Some(0));
+  assert_eq!(it.map(|x| x.0).collect::<Vec<_>>(), (1..49).collect::<Vec<_>>());
+  assert_eq!(counter.load(AtomicOrdering::SeqCst), 0, "uniquely owned values are moved out");
+
+  assert_eq!(TernaryTreeList::<usize>::Empty.into_iter().next(), None);
+
+  Ok(())
+}
+
+#[test]
+fn collect_and_extend() -> Result<(), TernaryTreeError> {
+  let data: TernaryTreeList<usize> = (0..100).collect();
+  data.check_structure()?;
+  assert!(data.eq_shape(&TernaryTreeList::from_chunked(0..100)));
+  assert_eq!(data.stats().leaves, 0);
+  assert_eq!(TernaryTreeList::<usize>::from_iter(vec![]), TernaryTreeList::Empty);
+
+  let mut extended = data.clone();
+  extended.extend(100..140);
+  extended.check_structure()?;
+  assert_eq!(extended.to_vec(), (0..140).collect::<Vec<_>>());
+  // the last chunk is filled before new chunks are added
+  assert_eq!(extended.stats().chunks, 5);
+
+  let appended = data.append_iter(vec![]);
+  assert!(appended.eq_shape(&data));
+  assert_eq!(TernaryTreeList::Empty.append_iter(0..5).to_vec(), vec![0, 1, 2, 3, 4]);
+
+  Ok(())
+}
+
+#[test]
+fn extend_keeps_depth() -> Result<(), TernaryTreeError> {
+  let data: TernaryTreeList<usize> = (0..10000).collect();
+  let depth = data.structure_report().max_depth;
+  for size in [1, 10, 100, 1000, 10000, 30000] {
+    let appended = data.append_iter(0..size);
+    appended.check_structure()?;
+    assert_eq!(appended.len(), 10000 + size);
+    let tail_depth = (0..size).collect::<TernaryTreeList<_>>().structure_report().max_depth;
+    let report = appended.structure_report();
+    assert!(report.max_depth <= depth.max(tail_depth) + 1, "appending {size}: {report:?}");
+  }
+
+  // tails are joined at the right side, so many small extends grow the tree like a 2-3 tree
+  let mut extended = TernaryTreeList::Empty;
+  for idx in 0..10000 {
+    extended.extend([idx]);
+  }
+  assert_eq!(extended.to_vec(), (0..10000).collect::<Vec<_>>());
+  let report = extended.structure_report();
+  assert!(report.max_depth <= 14, "{report:?}");
+
+  let mut extended = data.clone();
+  for idx in 0..10000 {
+    extended.extend([idx, idx]);
+  }
+  let report = extended.structure_report();
+  assert!(report.max_depth <= depth + 1, "{report:?}");
+
+  Ok(())
+}
+
+#[test]
+fn iter_range() -> Result<(), TernaryTreeError> {
+  let mut data = TernaryTreeList::Empty;
+  for idx in 0..40 {
+    data = data.push_right(idx);
+  }
+  let list40: Vec<usize> = (0..40).collect();
+
+  for i in 0..=40 {
+    for j in i..=40 {
+      assert_eq!(data.iter_range(i..j)?.copied().collect::<Vec<_>>(), list40[i..j]);
+      assert_eq!(
+        data.iter_range(i..j)?.rev().copied().collect::<Vec<_>>(),
+        data.slice(i, j)?.reverse().to_vec()
+      );
+      assert_eq!(data.iter_range(i..j)?.len(), j - i);
+    }
+  }
+
+  assert_eq!(data.iter_range(..)?.count(), 40);
+  assert_eq!(data.iter_range(38..)?.copied().collect::<Vec<_>>(), vec![38, 39]);
+  assert_eq!(data.iter_range(..=2)?.copied().collect::<Vec<_>>(), vec![0, 1, 2]);
+  assert_eq!(
+    data.iter_range(3..41).err(),
+    Some(TernaryTreeError::InvalidRange { start: 3, end: 41 })
+  );
+  #[allow(clippy::reversed_empty_ranges)]
+  let reversed = data.iter_range(5..3).err();
+  assert_eq!(reversed, Some(TernaryTreeError::InvalidRange { start: 5, end: 3 }));
+
+  let empty = TernaryTreeList::<usize>::Empty;
+  assert_eq!(empty.iter_range(0..0)?.next(), None);
+  assert!(empty.iter_range(0..1).is_err());
+
+  Ok(())
+}
+
+#[test]
+fn ranges() -> Result<(), TernaryTreeError> {
+  let data = TernaryTreeList::from(&[1, 2, 3, 4, 5, 6, 7, 8]);
+  let empty = TernaryTreeList::<usize>::Empty;
+
+  assert_eq!(data.range(2..5)?, TernaryTreeList::from(&[3, 4, 5]));
+  assert_eq!(data.range(2..=5)?, TernaryTreeList::from(&[3, 4, 5, 6]));
+  assert_eq!(data.range(6..)?, TernaryTreeList::from(&[7, 8]));
+  assert_eq!(data.range(..2)?, TernaryTreeList::from(&[1, 2]));
+  assert!(data.range(..)?.eq_shape(&data));
+  assert_eq!(data.range(8..8)?, TernaryTreeList::Empty);
+  assert_eq!(data.range(3..9), Err(TernaryTreeError::InvalidRange { start: 3, end: 9 }));
+
+  assert_eq!(data.get_range(1..3), Some(TernaryTreeList::from(&[2, 3])));
+  assert_eq!(data.get_range(9..), None);
+
+  // empty list behaves like any other list, only empty ranges at 0 are accepted
+  assert_eq!(empty.range(0..0)?, TernaryTreeList::Empty);
+  assert_eq!(empty.slice(0, 0)?, TernaryTreeList::Empty);
+  assert_eq!(empty.take(0)?, TernaryTreeList::Empty);
+  assert_eq!(empty.skip(0)?, TernaryTreeList::Empty);
+  assert_eq!(empty.skip(1), Err(TernaryTreeError::InvalidRange { start: 1, end: 0 }));
+  assert_eq!(empty.take(1), Err(TernaryTreeError::InvalidRange { start: 0, end: 1 }));
+  assert_eq!(empty.get_range(..1), None);
+
+  Ok(())
+}
+
+#[test]
+fn lexicographic_order() -> Result<(), TernaryTreeError> {
+  use std::cmp::Ordering;
+
+  let a = TernaryTreeList::from(&[2]);
+  let b = TernaryTreeList::from(&[1, 5]);
+  let c = TernaryTreeList::from(&[1, 5, 0]);
+  let empty = TernaryTreeList::<usize>::Empty;
+
+  assert_eq!(a.cmp_lexicographic(&b), Ordering::Greater);
+  assert_eq!(b.cmp_lexicographic(&c), Ordering::Less);
+  assert_eq!(c.cmp_lexicographic(&c.clone()), Ordering::Equal);
+  assert_eq!(empty.cmp_lexicographic(&b), Ordering::Less);
+  assert_eq!(vec![2].cmp(&vec![1, 5]), a.cmp_lexicographic(&b));
+
+  let fs = TernaryTreeList::from(&[1.0, f64::NAN]);
+  assert_eq!(
+    fs.partial_cmp_lexicographic(&TernaryTreeList::from(&[0.5])),
+    Some(Ordering::Greater)
+  );
+  assert_eq!(fs.partial_cmp_lexicographic(&fs.clone()), None);
+
+  if cfg!(feature = "lexicographic-ord") {
+    assert!(a > b);
+  } else {
+    assert!(a < b);
+  }
+  assert!(b < c);
+  assert!(empty < a);
+
+  Ok(())
+}
+
+#[test]
+fn compare_shared_versions() -> Result<(), TernaryTreeError> {
+  use std::cmp::Ordering;
+
+  let data: TernaryTreeList<usize> = (0..1000).collect();
+  let changed = data.assoc(500, 0)?;
+  let same = data.assoc(500, 500)?;
+
+  assert!(data != changed);
+  assert!(data == same);
+  assert_eq!(data.cmp(&changed), Ordering::Greater);
+  assert_eq!(changed.cmp(&data), Ordering::Less);
+  assert_eq!(data.cmp(&same), Ordering::Equal);
+  assert_eq!(data.partial_cmp(&changed), Some(Ordering::Greater));
+
+  // different shapes fall back to elements
+  let pushed = (0..1000).fold(TernaryTreeList::Empty, |acc, x| acc.push_right(x));
+  assert!(!pushed.eq_shape(&data));
+  assert!(pushed == data);
+  assert_eq!(pushed.cmp(&changed), Ordering::Greater);
+
+  assert!(!data.eq_shared(&changed));
+  assert!(data.eq_shared(&same));
+  assert!(pushed.eq_shared(&data));
+  assert!(TernaryTreeList::<usize>::Empty.eq_shared(&TernaryTreeList::Empty));
+  assert!(!data.eq_shared(&data.drop_right()));
+
+  Ok(())
+}
+
+#[derive(Debug, Clone)]
+struct CmpCounted(usize, Arc<AtomicUsize>);
+
+impl PartialEq for CmpCounted {
+  fn eq(&self, other: &Self) -> bool {
+    self.1.fetch_add(1, AtomicOrdering::SeqCst);
+    self.0 == other.0
+  }
+}
+
+impl Eq for CmpCounted {}
+
+impl PartialOrd for CmpCounted {
+  fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for CmpCounted {
+  fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    self.1.fetch_add(1, AtomicOrdering::SeqCst);
+    self.0.cmp(&other.0)
+  }
+}
+
+#[test]
+fn compare_skips_shared_subtrees() -> Result<(), TernaryTreeError> {
+  use std::cmp::Ordering;
+
+  let counter = Arc::new(AtomicUsize::new(0));
+  let data: TernaryTreeList<CmpCounted> = (0..10000).map(|idx| CmpCounted(idx, counter.clone())).collect();
+  let changed = data.assoc(5000, CmpCounted(0, counter.clone()))?;
+
+  assert!(!data.eq_shared(&changed));
+  assert!(data.eq_shared(&data.clone()));
+  assert!(
+    counter.load(AtomicOrdering::SeqCst) <= CHUNK_SIZE,
+    "only the changed chunk is compared"
+  );
+
+  counter.store(0, AtomicOrdering::SeqCst);
+  assert_eq!(data.cmp(&changed), Ordering::Greater);
+  assert!(
+    counter.load(AtomicOrdering::SeqCst) <= CHUNK_SIZE,
+    "only the changed chunk is compared"
+  );
+
+  counter.store(0, AtomicOrdering::SeqCst);
+  assert!(data != changed);
+  assert_eq!(
+    counter.load(AtomicOrdering::SeqCst),
+    5001,
+    "== compares elements until the difference"
+  );
+
+  Ok(())
+}
+
+#[cfg(feature = "cached-hash")]
+#[derive(Debug, Clone)]
+struct HashCounted(usize, Arc<AtomicUsize>);
+
+#[cfg(feature = "cached-hash")]
+impl std::hash::Hash for HashCounted {
+  fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    self.1.fetch_add(1, AtomicOrdering::SeqCst);
+    self.0.hash(state);
+  }
+}
+
+#[cfg(feature = "cached-hash")]
+#[test]
+fn cached_hash() -> Result<(), TernaryTreeError> {
+  let data: TernaryTreeList<usize> = (0..1000).collect();
+  let pushed = (0..1000).fold(TernaryTreeList::Empty, |acc, x| acc.push_right(x));
+  let joined = TernaryTreeList::concat(&[(0..400usize).collect(), (400..1000).collect()]);
+  assert!(!pushed.eq_shape(&data));
+  assert_eq!(data.cached_hash(), pushed.cached_hash());
+  assert_eq!(data.cached_hash(), joined.cached_hash());
+  assert_ne!(data.cached_hash(), data.assoc(500, 0)?.cached_hash());
+  assert_ne!(data.cached_hash(), data.drop_right().cached_hash());
+  assert_ne!(
+    TernaryTreeList::<usize>::Empty.cached_hash(),
+    TernaryTreeList::from(&[0]).cached_hash()
+  );
+
+  let counter = Arc::new(AtomicUsize::new(0));
+  let data = TernaryTreeList::from((0..1000).map(|idx| HashCounted(idx, counter.clone())).collect::<Vec<_>>());
+  let first = data.cached_hash();
+  assert_eq!(counter.load(AtomicOrdering::SeqCst), 1000);
+
+  counter.store(0, AtomicOrdering::SeqCst);
+  assert_eq!(data.clone().cached_hash(), first);
+  assert_eq!(counter.load(AtomicOrdering::SeqCst), 0, "digests are reused");
+
+  let changed = data.assoc(500, HashCounted(0, counter.clone()))?;
+  assert_ne!(changed.cached_hash(), first);
+  assert!(
+    counter.load(AtomicOrdering::SeqCst) < 10,
+    "only leaves next to the changed path are hashed"
+  );
+
+  Ok(())
+}
+
+#[cfg(feature = "cached-hash")]
+#[test]
+// caches inside branches are `OnceLock`s, filling them never changes the hash
+#[allow(clippy::mutable_key_type)]
+fn cached_hash_list_as_key() -> Result<(), TernaryTreeError> {
+  use im_ternary_tree::CachedHashList;
+  use std::collections::HashMap;
+
+  let data: TernaryTreeList<usize> = (0..1000).collect();
+  let pushed = (0..1000).fold(TernaryTreeList::Empty, |acc, x| acc.push_right(x));
+  let changed = data.assoc(500, 0)?;
+
+  let mut counts: HashMap<CachedHashList<usize>, usize> = HashMap::new();
+  *counts.entry(data.clone().into()).or_default() += 1;
+  *counts.entry(pushed.into()).or_default() += 1;
+  *counts.entry(changed.clone().into()).or_default() += 1;
+
+  assert_eq!(counts.len(), 2);
+  assert_eq!(counts[&CachedHashList(data)], 2);
+  assert_eq!(counts[&CachedHashList::from(changed)], 1);
+
+  Ok(())
+}
+
+#[test]
+fn hash_contract() -> Result<(), TernaryTreeError> {
+  let empty: &[usize] = &[];
+  assert_eq!(hash_of(&TernaryTreeList::<usize>::Empty), hash_of(empty));
+  assert_eq!(hash_of(&TernaryTreeList::from(&[1usize, 2, 3])), hash_of(&[1usize, 2, 3][..]));
+
+  // length prefix separates nested lists
+  let a = TernaryTreeList::from(vec![TernaryTreeList::from(&[1]), TernaryTreeList::from(&[2])]);
+  let b = TernaryTreeList::from(vec![TernaryTreeList::from(&[1, 2]), TernaryTreeList::Empty]);
+  assert_ne!(hash_of(&a), hash_of(&b));
+
+  Ok(())
+}
+
+#[test]
+fn chunked_leaves() -> Result<(), TernaryTreeError> {
+  use im_ternary_tree::CHUNK_SIZE;
+
+  let data = TernaryTreeList::from_chunked(0..4);
+  assert_eq!(data.format_inline(), "[0 1 2 3]");
+  assert_eq!(data.push_right(4).push_left(5).format_inline(), "[5 0 1 2 3 4]");
+  assert_eq!(data.assoc(1, 10)?.format_inline(), "[0 10 2 3]");
+  assert_eq!(data.dissoc(1)?.format_inline(), "[0 2 3]");
+  assert_eq!(data.assoc_after(1, 10)?.format_inline(), "[0 1 10 2 3]");
+  assert_eq!(data.reverse().format_inline(), "[3 2 1 0]");
+  assert_eq!(data.slice(1, 3)?.format_inline(), "[1 2]");
+  assert_eq!(data.clone().split(1).1.format_inline(), "[1 2 3]");
+  assert_eq!(data.drop_left_shallow().format_inline(), "[1 2 3]");
+
+  // a full chunk is split when inserting
+  let full = TernaryTreeList::from_chunked(0..CHUNK_SIZE);
+  let inserted = full.assoc_before(2, 100)?;
+  assert_eq!(inserted.len(), CHUNK_SIZE + 1);
+  assert_eq!(inserted.get(2), Some(&100));
+  inserted.check_structure()?;
+
+  // chunks at both ends are filled before new chunks are added
+  let mut pushed = TernaryTreeList::from_chunked([0]);
+  for idx in 1..1000 {
+    pushed = pushed.push_right(idx).push_left(idx);
+  }
+  pushed.check_structure()?;
+  let expected: Vec<usize> = (1..1000).rev().chain(0..1000).collect();
+  assert_eq!(pushed.to_vec(), expected);
+  assert!(pushed.format_inline().contains("[999 998 "));
+  assert_eq!(pushed.index_of(&0), Some(999));
+  assert_eq!(pushed.last_index_of(&1), Some(998));
+  assert_eq!(pushed.find_index(Arc::new(|x| *x == 0)), Some(999));
+  assert_eq!(
+    pushed.iter_range(990..1010)?.copied().collect::<Vec<_>>(),
+    expected[990..1010].to_vec()
+  );
+  assert_eq!(pushed.iter_range(3..5)?.rev().copied().collect::<Vec<_>>(), vec![995, 996]);
+  assert_eq!(pushed.map(Arc::new(|x| x * 2)).get(2), Some(&1994));
+
+  let plain = TernaryTreeList::from(&expected);
+  assert!(pushed == plain);
+  #[cfg(feature = "cached-hash")]
+  assert_eq!(pushed.cached_hash(), plain.cached_hash());
+  assert_eq!(pushed.cmp_lexicographic(&plain), std::cmp::Ordering::Equal);
+
+  Ok(())
+}
+
+#[test]
+fn transient_builder() -> Result<(), TernaryTreeError> {
+  // a new builder starts with a chunk
+  let mut builder = TernaryTreeListBuilder::new();
+  builder.push_right(0);
+  let mut persistent = TernaryTreeList::from_chunked([0]);
+  for idx in 1..1000 {
+    builder.push_right(idx);
+    persistent = persistent.push_right(idx);
+    if idx % 3 == 0 {
+      builder.push_left(idx);
+      persistent = persistent.push_left(idx);
+    }
+  }
+  assert_eq!(builder.len(), persistent.len());
+  let built = builder.freeze();
+  built.check_structure()?;
+  assert!(built.eq_shape(&persistent));
+  assert_eq!(built.stats().leaves, 0);
+
+  let mut builder = built.transient();
+  let mut persistent = built.clone();
+  for idx in 0..400 {
+    if idx % 2 == 0 {
+      assert_eq!(builder.pop_left(), persistent.first().copied());
+      persistent = persistent.drop_left();
+    } else {
+      assert_eq!(builder.pop_right(), persistent.last().copied());
+      persistent = persistent.drop_right();
+    }
+  }
+  builder.assoc(10, 0)?;
+  persistent = persistent.assoc(10, 0)?;
+  assert_eq!(
+    builder.assoc(10000, 0),
+    Err(TernaryTreeError::IndexOutOfBounds { index: 10000, len: 933 })
+  );
+  let edited = builder.freeze();
+  edited.check_structure()?;
+  assert!(edited.eq_shape(&persistent));
+  #[cfg(feature = "cached-hash")]
+  assert_eq!(edited.cached_hash(), persistent.cached_hash());
+  // original list is not affected
+  assert_eq!(built.len(), 1333);
+  assert_eq!(built.first(), Some(&999));
+
+  let mut builder = TernaryTreeList::from(&[1, 2]).transient();
+  assert_eq!(builder.pop_right(), Some(2));
+  assert_eq!(builder.pop_right(), Some(1));
+  assert_eq!(builder.pop_left(), None);
+  assert!(builder.is_empty());
+
+  // elements are not copied when nodes are owned by the builder
+  let counter = Arc::new(AtomicUsize::new(0));
+  let mut builder = TernaryTreeListBuilder::from(TernaryTreeList::from_chunked(
+    (0..100).map(|idx| CloneCounted(idx, counter.clone())),
+  ));
+  counter.store(0, AtomicOrdering::SeqCst);
+  for idx in 0..1000 {
+    builder.push_right(CloneCounted(idx, counter.clone()));
+    builder.push_left(CloneCounted(idx, counter.clone()));
+  }
+  builder.pop_left();
+  builder.pop_right();
+  builder.assoc(500, CloneCounted(0, counter.clone()))?;
+  assert_eq!(counter.load(AtomicOrdering::SeqCst), 0);
+  let chunked = builder.freeze();
+  chunked.check_structure()?;
+  assert_eq!(chunked.len(), 2098);
+
+  Ok(())
+}
+
+#[test]
+fn mut_methods() -> Result<(), TernaryTreeError> {
+  let mut data = TernaryTreeList::Empty;
+  let mut persistent = TernaryTreeList::Empty;
+  for idx in 0..200 {
+    data.push_right_mut(idx);
+    persistent = persistent.push_right(idx);
+    if idx % 4 == 0 {
+      data.push_left_mut(idx);
+      persistent = persistent.push_left(idx);
+    }
+  }
+  data.check_structure()?;
+  assert!(data.eq_shape(&persistent));
+
+  // clones keep their values while the list is edited
+  let snapshot = data.clone();
+  data.assoc_mut(3, 1000)?;
+  assert_eq!(
+    data.assoc_mut(300, 0),
+    Err(TernaryTreeError::IndexOutOfBounds { index: 300, len: 250 })
+  );
+  assert_eq!(data.pop_left_mut(), Some(196));
+  assert_eq!(data.pop_right_mut(), Some(199));
+  data.drop_left_mut();
+  data.drop_right_mut();
+  data.check_structure()?;
+  assert_eq!(data.len(), 246);
+  assert_eq!(data.get(1), Some(&1000));
+  assert!(data.eq_shape(&snapshot.assoc(3, 1000)?.drop_left().drop_right().drop_left().drop_right()));
+  assert_eq!(snapshot.len(), 250);
+  assert_eq!(snapshot.get(4), Some(&180));
+  assert_eq!(snapshot, persistent);
+
+  let mut data = TernaryTreeList::from(&[1]);
+  assert_eq!(data.pop_left_mut(), Some(1));
+  assert_eq!(data.pop_left_mut(), None);
+  data.drop_right_mut();
+  assert_eq!(data.assoc_mut(0, 1), Err(TernaryTreeError::EmptyList));
+  assert!(data.is_empty());
+
+  Ok(())
+}
+
+#[test]
+fn pop_both_ends() -> Result<(), TernaryTreeError> {
+  let data = TernaryTreeList::from(&(0..20).collect::<Vec<usize>>());
+  let (item, rest) = data.pop_left().expect("not empty");
+  assert_eq!(item, 0);
+  assert!(rest.eq_shape(&data.drop_left()));
+  let (item, rest) = data.pop_right().expect("not empty");
+  assert_eq!(item, 19);
+  assert!(rest.eq_shape(&data.drop_right()));
+  assert_eq!(data.len(), 20);
+
+  // consume as a queue and as a stack
+  let mut queue = TernaryTreeList::from_chunked(0..100usize);
+  let mut popped = vec![];
+  while let Some((item, rest)) = queue.pop_left() {
+    rest.check_structure()?;
+    popped.push(item);
+    queue = rest;
+  }
+  assert_eq!(popped, (0..100).collect::<Vec<usize>>());
+
+  let mut stack = TernaryTreeList::from(&(0..100).collect::<Vec<usize>>());
+  popped.clear();
+  while let Some((item, rest)) = stack.pop_right() {
+    rest.check_structure()?;
+    popped.push(item);
+    stack = rest;
+  }
+  assert_eq!(popped, (0..100).rev().collect::<Vec<usize>>());
+
+  assert_eq!(TernaryTreeList::<usize>::Empty.pop_left(), None);
+  assert_eq!(TernaryTreeList::from(&[1]).pop_right(), Some((1, TernaryTreeList::Empty)));
+
+  Ok(())
+}
+
+#[test]
+fn balanced_middle_insert() -> Result<(), TernaryTreeError> {
+  // inserting at the same place used to grow a chain of branches as deep as the list
+  let mut data = TernaryTreeList::Empty;
+  let mut expected = vec![];
+  for idx in 0..20000 {
+    data = data.insert(idx / 2, idx, false)?;
+    expected.insert(idx / 2, idx);
+  }
+  data.check_structure()?;
+  assert_eq!(data.to_vec(), expected);
+
+  for idx in 0..5000 {
+    data = data.insert(10000, idx, true)?;
+    expected.insert(10001, idx);
+  }
+  data.check_structure()?;
+  assert_eq!(data.to_vec(), expected);
+
+  for _ in 0..20000 {
+    data = data.dissoc(data.len() / 2)?;
+    expected.remove(expected.len() / 2);
+  }
+  data.check_structure()?;
+  assert_eq!(data.to_vec(), expected);
+
+  let mut chunked = TernaryTreeList::from_chunked(0..1000);
+  for idx in 0..5000 {
+    chunked = chunked.insert(500, idx, false)?;
+  }
+  chunked.check_structure()?;
+  assert_eq!(chunked.len(), 6000);
+  assert_eq!(chunked.get(500), Some(&4999));
+
+  Ok(())
+}
+
+#[test]
+fn structure_report() -> Result<(), TernaryTreeError> {
+  let empty = TernaryTreeList::<usize>::Empty.structure_report();
+  assert!(empty.is_ok());
+  assert_eq!(empty.len, 0);
+  assert_eq!(empty.max_depth, 0);
+
+  let pushed = (0..1000).fold(TernaryTreeList::Empty, |acc, idx| acc.push_right(idx));
+  let report = pushed.structure_report();
+  assert!(report.is_ok(), "{report:?}");
+  assert_eq!(report.len, 1000);
+  assert_eq!(report.units, 1000);
+  assert_eq!(report.balanced_depth, 7);
+  assert!(report.max_depth >= report.balanced_depth && report.max_depth <= report.depth_limit);
+  assert!(report.left_edge_depth <= 2 && report.right_edge_depth <= 2);
+
+  let chunked = TernaryTreeList::from_chunked(0..1000).structure_report();
+  assert!(chunked.is_ok(), "{chunked:?}");
+  assert_eq!(chunked.units, 32);
+
+  assert!(report.finger_fill <= 100, "{report:?}");
+
+  // pops leave fuller sides than pushes would make, which is fine
+  for n in 2..400 {
+    let pushed = (0..n).fold(TernaryTreeList::Empty, |acc, idx| acc.push_right(idx));
+    for popped in [
+      pushed.drop_right(),
+      pushed.drop_left(),
+      pushed.pop_right().unwrap().1,
+      pushed.drop_right().drop_right(),
+    ] {
+      let report = popped.structure_report();
+      assert!(report.is_ok(), "{n} {report:?}");
+    }
+  }
+  let report = TernaryTreeList::from(&(0..19).collect::<Vec<usize>>()).structure_report();
+  assert!(report.is_ok(), "{report:?}");
+  let popped = (0..20).fold(TernaryTreeList::Empty, |acc, idx| acc.push_right(idx)).drop_right();
+  assert_eq!(popped.structure_report().issues, vec![]);
+
+  // concatenated lists are valid, but large sides near the ends are not where pushing expects them
+  let data = TernaryTreeList::from(&(0..100).collect::<Vec<usize>>());
+  let joined = TernaryTreeList::concat(&[data.clone(), data.clone(), data]);
+  let report = joined.structure_report();
+  assert!(report.is_ok());
+  assert_eq!(report.finger_fill, 3333);
+
+  // a list too deep for its size
+  let deep = TernaryTreeList::<usize>::parse_inline(&format!("{}0{}", "(0 ".repeat(40), " 0)".repeat(40))).unwrap();
+  let report = deep.structure_report();
+  assert!(report.is_valid());
+  assert!(!report.is_ok());
+  assert_eq!(report.issues, vec![StructureIssue::TooDeep { depth: 40, limit: 14 }]);
+  assert!(!report.issues[0].is_broken());
+
+  // ends of the list too deep, where pushes and pops go
+  let deep = TernaryTreeList::<usize>::parse_inline(&format!("{}0{}", "(".repeat(40), " 0)".repeat(40))).unwrap();
+  let report = deep.structure_report();
+  assert!(report.is_valid());
+  assert_eq!(
+    report.issues,
+    vec![
+      StructureIssue::TooDeep { depth: 40, limit: 12 },
+      StructureIssue::FingerTooDeep {
+        offset: 0,
+        depth: 40,
+        limit: 12
+      },
+    ]
+  );
+  assert!(report.issues.iter().all(|x| !x.is_broken()));
+  let deep = TernaryTreeList::<usize>::parse_inline(&format!("{}0{}", "(0 ".repeat(40), ")".repeat(40))).unwrap();
+  assert_eq!(
+    deep.structure_report().issues[1],
+    StructureIssue::FingerTooDeep {
+      offset: 40,
+      depth: 40,
+      limit: 12
+    }
+  );
+
+  Ok(())
+}
+
+#[test]
+fn tree_stats() -> Result<(), TernaryTreeError> {
+  assert_eq!(TernaryTreeList::<usize>::Empty.stats(), TreeStats::default());
+
+  let data = TernaryTreeList::from(&[1, 2, 3, 4]);
+  let stats = data.stats();
+  assert_eq!(stats.len, 4);
+  assert_eq!(stats.leaves, 4);
+  assert_eq!(stats.chunks, 0);
+  assert_eq!(stats.branch2 + stats.branch3, 2);
+  assert_eq!((stats.min_depth, stats.max_depth), (1, 2));
+  assert!(stats.avg_depth > 1.0 && stats.avg_depth < 2.0);
+  assert_eq!(stats.shared_nodes, 0);
+  assert!(stats.heap_bytes > 0);
+
+  let chunked = TernaryTreeList::from_chunked(0..100usize).stats();
+  assert_eq!((chunked.leaves, chunked.chunks), (0, 4));
+  assert!(chunked.heap_bytes >= 100 * std::mem::size_of::<usize>());
+
+  // after assoc, only nodes on the changed path are new
+  let origin = TernaryTreeList::from(&(0..1000).collect::<Vec<usize>>());
+  let updated = origin.assoc(500, 0)?;
+  let depth = updated.stats().max_depth;
+  assert!(origin.stats().shared_nodes > 0);
+  let sharing = TernaryTreeList::sharing_stats([&origin, &updated]);
+  assert_eq!(sharing.lists, 2);
+  let nodes = origin.stats();
+  assert_eq!(sharing.total_nodes, 2 * (nodes.leaves + nodes.branch2 + nodes.branch3));
+  assert!(sharing.unique_nodes <= sharing.total_nodes / 2 + depth + 1);
+  assert!(sharing.arc_shared_nodes > 0);
+  assert!(sharing.unique_heap_bytes < sharing.total_heap_bytes);
+  assert!(sharing.sharing_ratio() > 0.45);
+
+  let alone = TernaryTreeList::sharing_stats(&[TernaryTreeList::from(&[1, 2, 3]), TernaryTreeList::Empty]);
+  assert_eq!(alone.lists, 2);
+  assert_eq!(alone.total_nodes, alone.unique_nodes);
+  assert_eq!(alone.sharing_ratio(), 0.0);
+
+  Ok(())
+}
+
+#[test]
+fn dot_export() -> Result<(), TernaryTreeError> {
+  let data = TernaryTreeList::from(&[1, 2, 3, 4, 5]);
+  let dot = data.to_dot();
+  assert!(dot.starts_with("digraph TernaryTree {\n"));
+  assert!(dot.contains("  v0 -> n0;\n"));
+  assert!(dot.contains("  n0 [label=\"Branch3 (5)\"];\n"));
+  assert!(dot.contains("  n1 [label=\"1\"];\n"));
+  assert!(dot.contains("  n0 -> n1 [label=\"left\"];\n"));
+  assert!(!dot.contains("lightgray"));
+  assert!(dot.ends_with("}\n"));
+
+  // nodes not changed by assoc are drawn once for both versions
+  let updated = data.assoc(4, 10)?;
+  let dot = TernaryTreeList::versions_to_dot([&data, &updated, &TernaryTreeList::Empty]);
+  assert!(dot.contains("  v1 -> n7;\n"));
+  assert!(dot.contains("  n2 [label=\"Branch3 (3)\", style=filled, fillcolor=lightgray];\n"));
+  assert!(dot.contains("  n7 -> n2 [label=\"middle\"];\n"));
+  assert!(dot.contains("  n8 [label=\"10\"];\n"));
+  assert!(dot.contains("  v2 [label=\"v2 (empty)\", shape=plaintext];\n"));
+  assert_eq!(dot.matches("label=\"4\"").count(), 1);
+
+  let quoted = TernaryTreeList::from_chunked([String::from("say \"hi\"")]).to_dot();
+  assert!(quoted.contains(r#"n0 [label="[say \"hi\"]"];"#));
+
+  Ok(())
+}
+
+#[test]
+fn format_tree() -> Result<(), TernaryTreeError> {
+  let data = TernaryTreeList::from(&[1, 2, 3, 4, 5]);
+  let expected = "Branch3 size=5 depth=0
+|-- Leaf 1
+|-- Branch3 size=3 depth=1
+|   |-- Leaf 2
+|   |-- Leaf 3
+|   `-- Leaf 4
+`-- Leaf 5
+";
+  assert_eq!(data.format_tree(&FormatTreeOptions::default()), expected);
+
+  let opts = FormatTreeOptions {
+    max_depth: Some(0),
+    offsets: true,
+  };
+  assert_eq!(data.format_tree(&opts), "Branch3 size=5 depth=0 @0 ...\n");
+
+  let opts = FormatTreeOptions {
+    max_depth: Some(1),
+    offsets: true,
+  };
+  let chunked = TernaryTreeList::from_chunked(0..40);
+  let text = chunked.format_tree(&opts);
+  assert!(text.starts_with("Branch2 size=40 depth=0 @0\n|-- Chunk size=32 depth=1 [0 1 2"));
+  assert!(text.ends_with("`-- Chunk size=8 depth=1 [32 33 34 35 36 37 38 39] @32\n"));
+
+  assert_eq!(TernaryTreeList::<usize>::Empty.format_tree(&opts), "Empty\n");
+
+  Ok(())
+}
+
+#[test]
+fn parse_inline() -> Result<(), TernaryTreeError> {
+  let data = TernaryTreeList::<usize>::parse_inline("((1 2) 3 (4 (5 6) 7))").unwrap();
+  assert_eq!(data.format_inline(), "((1 2) 3 (4 (5 6) 7))");
+  assert_eq!(data.len(), 7);
+  assert_eq!(data.to_vec(), vec![1, 2, 3, 4, 5, 6, 7]);
+  assert!(data.check_structure().is_ok());
+
+  // shape is kept as written, even when it differs from what `from` builds
+  let from_slice = TernaryTreeList::from(&[1, 2, 3, 4, 5]);
+  let parsed = TernaryTreeList::parse_inline(&from_slice.format_inline()).unwrap();
+  assert!(parsed.eq_shape(&from_slice));
+  assert!(!TernaryTreeList::<usize>::parse_inline("((1 2) 3 (4 5))").unwrap().eq_shape(&parsed));
+
+  let chunked = TernaryTreeList::from_chunked(0..40).push_left(100);
+  let parsed = TernaryTreeList::<usize>::parse_inline(&chunked.format_inline()).unwrap();
+  assert!(parsed.eq_shape(&chunked));
+
+  assert_eq!(TernaryTreeList::<usize>::parse_inline(" _ ").unwrap(), TernaryTreeList::Empty);
+  assert_eq!(TernaryTreeList::<usize>::parse_inline("4").unwrap().to_vec(), vec![4]);
+  assert_eq!(TernaryTreeList::<String>::parse_inline("(_ a)").unwrap().to_vec(), vec!["_", "a"]);
+
+  assert_eq!(TernaryTreeList::<usize>::parse_inline(""), Err(ParseInlineError::UnexpectedEnd));
+  assert_eq!(
+    TernaryTreeList::<usize>::parse_inline("(1 (2 3)"),
+    Err(ParseInlineError::UnexpectedEnd)
+  );
+  assert_eq!(
+    TernaryTreeList::<usize>::parse_inline("(1 2))"),
+    Err(ParseInlineError::UnexpectedChar { offset: 5 })
+  );
+  assert_eq!(
+    TernaryTreeList::<usize>::parse_inline("(1 x)"),
+    Err(ParseInlineError::InvalidValue { offset: 3 })
+  );
+  assert_eq!(
+    TernaryTreeList::<usize>::parse_inline("(1 (2 3 4 5))"),
+    Err(ParseInlineError::InvalidBranch { offset: 3, children: 4 })
+  );
+  assert_eq!(
+    TernaryTreeList::<usize>::parse_inline("(1)"),
+    Err(ParseInlineError::InvalidBranch { offset: 0, children: 1 })
+  );
+  assert_eq!(
+    TernaryTreeList::<usize>::parse_inline("(1 [])"),
+    Err(ParseInlineError::InvalidChunk { offset: 3, size: 0 })
+  );
+  assert_eq!(
+    TernaryTreeList::<usize>::parse_inline("[1 (2 3)]"),
+    Err(ParseInlineError::UnexpectedChar { offset: 3 })
+  );
+
+  // deep nesting is an error rather than running out of stack
+  assert_eq!(
+    TernaryTreeList::<usize>::parse_inline(&"(".repeat(100_000)),
+    Err(ParseInlineError::TooDeep { offset: 1024 })
+  );
+  let nested = |n: usize| format!("{}0{}", "(0 ".repeat(n), " 0)".repeat(n));
+  assert_eq!(
+    TernaryTreeList::<usize>::parse_inline(&nested(2000)),
+    Err(ParseInlineError::TooDeep { offset: 3072 })
+  );
+  assert_eq!(TernaryTreeList::<usize>::parse_inline(&nested(1024)).unwrap().len(), 2049);
+
+  Ok(())
+}
+
+struct U32Codec;
+
+impl SnapshotCodec<u32> for U32Codec {
+  fn encode(&self, item: &u32, out: &mut Vec<u8>) {
+    out.extend_from_slice(&item.to_le_bytes());
+  }
+
+  fn decode(&self, bytes: &[u8]) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+  }
+}
+
+#[test]
+fn snapshot() -> Result<(), TernaryTreeError> {
+  let base = TernaryTreeList::from_chunked(0..100u32).push_left(1000);
+  let updated = base.assoc(50, 7)?;
+  let pushed = base.push_right(2000);
+  let versions = [base, updated, TernaryTreeList::Empty, pushed];
+
+  let bytes = TernaryTreeList::write_snapshot(&versions, &U32Codec);
+  let loaded = TernaryTreeList::read_snapshot(&bytes, &U32Codec).unwrap();
+  assert_eq!(loaded.len(), 4);
+  for (x, y) in loaded.iter().zip(&versions) {
+    assert!(x.eq_shape(y));
+    assert!(x.check_structure().is_ok());
+  }
+  assert_eq!(loaded[2], TernaryTreeList::Empty);
+
+  // nodes shared between versions are written once and shared again after loading
+  let stats = TernaryTreeList::sharing_stats(&loaded);
+  let expected = TernaryTreeList::sharing_stats(&versions);
+  assert_eq!(
+    (stats.total_nodes, stats.unique_nodes),
+    (expected.total_nodes, expected.unique_nodes)
+  );
+  assert!(stats.unique_nodes < stats.total_nodes);
+  let single = TernaryTreeList::write_snapshot([&versions[0]], &U32Codec);
+  assert!(bytes.len() < single.len() * 2);
+
+  assert_eq!(
+    TernaryTreeList::<u32>::read_snapshot(&TernaryTreeList::<u32>::write_snapshot([], &U32Codec), &U32Codec).unwrap(),
+    vec![]
+  );
+
+  assert_eq!(TernaryTreeList::read_snapshot(b"TTS", &U32Codec), Err(SnapshotError::InvalidHeader));
+  assert_eq!(
+    TernaryTreeList::read_snapshot(&bytes[..bytes.len() - 1], &U32Codec),
+    Err(SnapshotError::UnexpectedEnd)
+  );
+  let mut trailing = bytes.clone();
+  trailing.push(0);
+  assert_eq!(
+    TernaryTreeList::read_snapshot(&trailing, &U32Codec),
+    Err(SnapshotError::TrailingBytes { offset: bytes.len() })
+  );
+  // one leaf of 2 bytes, then a branch pointing to a node after it
+  let broken = b"TTS\x01\x02\x00\x02ab\x02\x00\x05\x01\x02";
+  assert_eq!(
+    TernaryTreeList::read_snapshot(broken, &U32Codec),
+    Err(SnapshotError::InvalidValue { offset: 7 })
+  );
+  let broken = b"TTS\x01\x02\x00\x04abcd\x02\x00\x05\x01\x02";
+  assert_eq!(
+    TernaryTreeList::read_snapshot(broken, &U32Codec),
+    Err(SnapshotError::InvalidReference { offset: 13, id: 5 })
+  );
+  fn push_number(out: &mut Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+      out.push((n as u8 & 0x7f) | 0x80);
+      n >>= 7;
+    }
+    out.push(n as u8);
+  }
+  // a chain of branches deeper than allowed is an error rather than running out of stack later
+  let mut chain = b"TTS\x01".to_vec();
+  let count: usize = 300_000;
+  push_number(&mut chain, count);
+  chain.extend_from_slice(b"\x00\x04abcd");
+  let mut branch_offsets = vec![];
+  for id in 1..count {
+    branch_offsets.push(chain.len());
+    chain.push(2);
+    push_number(&mut chain, id - 1);
+    push_number(&mut chain, 0);
+  }
+  push_number(&mut chain, 1);
+  push_number(&mut chain, count);
+  assert_eq!(
+    TernaryTreeList::read_snapshot(&chain, &U32Codec),
+    Err(SnapshotError::TooDeep {
+      offset: branch_offsets[1024]
+    })
+  );
+
+  let broken = b"TTS\x01\x01\x07";
+  assert_eq!(
+    TernaryTreeList::read_snapshot(broken, &U32Codec),
+    Err(SnapshotError::InvalidTag { offset: 5, tag: 7 })
+  );
+
+  Ok(())
+}
@@ -1,6 +1,75 @@
-use im_ternary_tree::TernaryTreeList;
+mod common;
+
+use common::{ByteCodec, hash_of};
+use im_ternary_tree::{StructureIssue, TernaryTreeList, TernaryTreeListBuilder};
 use proptest::prelude::*;
 
+/// edits a chunked list and a `Vec` in the same ways, checking structure after each step
+fn edit_chunked(xs: Vec<u8>, ops: Vec<(u8, usize, u8)>) -> Result<(TernaryTreeList<u8>, Vec<u8>), TestCaseError> {
+  let mut tree = TernaryTreeList::from_chunked(xs.clone());
+  let mut vec = xs;
+
+  for (op, idx, value) in ops {
+    let pos = if vec.is_empty() { 0 } else { idx % vec.len() };
+    match op {
+      0 => {
+        tree = tree.push_right(value);
+        vec.push(value);
+      }
+      1 => {
+        tree = tree.push_left(value);
+        vec.insert(0, value);
+      }
+      2 => {
+        tree = tree.drop_left();
+        if !vec.is_empty() {
+          vec.remove(0);
+        }
+      }
+      3 => {
+        tree = tree.drop_right();
+        vec.pop();
+      }
+      4 if !vec.is_empty() => {
+        tree = tree.assoc(pos, value).unwrap();
+        vec[pos] = value;
+      }
+      5 if !vec.is_empty() => {
+        tree = tree.dissoc(pos).unwrap();
+        vec.remove(pos);
+      }
+      6 if !vec.is_empty() => {
+        tree = tree.insert(pos, value, idx % 2 == 0).unwrap();
+        vec.insert(if idx % 2 == 0 { pos + 1 } else { pos }, value);
+      }
+      7 => {
+        let end = if vec.is_empty() {
+          0
+        } else {
+          pos + (idx / 7) % (vec.len() - pos + 1)
+        };
+        tree = tree.range(pos.min(end)..end).unwrap();
+        vec = vec[pos.min(end)..end].to_vec();
+      }
+      8 => {
+        tree = tree.drop_left_shallow().drop_right_shallow();
+        if !vec.is_empty() {
+          vec.remove(0);
+        }
+        vec.pop();
+      }
+      _ => {}
+    }
+    prop_assert!(tree.check_structure().is_ok());
+    prop_assert_eq!(tree.len(), vec.len());
+  }
+  Ok((tree, vec))
+}
+
+fn chunk_ops() -> impl Strategy<Value = Vec<(u8, usize, u8)>> {
+  prop::collection::vec((0..9u8, any::<usize>(), any::<u8>()), 0..200)
+}
+
 proptest! {
   #[test]
   fn test_push_right_matches_vec(initial_data in prop::collection::vec(any::<u8>(), 0..1000)) {
@@ -59,4 +128,329 @@ proptest! {
 
     prop_assert!(tree.is_empty(), "Tree should be empty after all elements are dropped");
   }
+
+  #[test]
+  fn test_drop_right_mirrors_drop_left(initial_data in prop::collection::vec(any::<u8>(), 1..100), pushes in prop::collection::vec(any::<bool>(), 0..100)) {
+    let mut tree = TernaryTreeList::from(initial_data);
+    for (idx, at_right) in pushes.into_iter().enumerate() {
+      tree = if at_right { tree.push_right(idx as u8) } else { tree.push_left(idx as u8) };
+    }
+
+    // reversing mirrors the shape, so dropping at both ends should restructure the same way
+    while tree.len() > 1 {
+      let mirrored = tree.reverse().drop_left().reverse();
+      tree = tree.drop_right();
+      prop_assert!(tree.eq_shape(&mirrored), "{} should be in shape of {}", tree.format_inline(), mirrored.format_inline());
+    }
+  }
+
+  #[test]
+  fn test_pop_matches_vec(initial_data in prop::collection::vec(any::<u8>(), 0..300), sides in prop::collection::vec(any::<bool>(), 300)) {
+    let mut tree = TernaryTreeList::from(initial_data.clone());
+    let mut vec = std::collections::VecDeque::from(initial_data);
+
+    for from_left in sides {
+      let popped = if from_left { tree.pop_left() } else { tree.pop_right() };
+      let expected = if from_left { vec.pop_front() } else { vec.pop_back() };
+      match popped {
+        Some((item, rest)) => {
+          prop_assert_eq!(Some(item), expected);
+          let dropped = if from_left { tree.drop_left() } else { tree.drop_right() };
+          prop_assert!(rest.eq_shape(&dropped));
+          tree = rest;
+        }
+        None => prop_assert_eq!(None, expected),
+      }
+      prop_assert_eq!(tree.len(), vec.len());
+    }
+  }
+
+  #[test]
+  fn test_iter_matches_vec(initial_data in prop::collection::vec(any::<u8>(), 0..1000)) {
+    let mut tree = TernaryTreeList::Empty;
+    for item in &initial_data {
+      tree = tree.push_right(*item);
+    }
+
+    prop_assert_eq!(tree.iter().len(), initial_data.len());
+    prop_assert_eq!(tree.iter().copied().collect::<Vec<_>>(), initial_data, "iterating should visit elements in order");
+  }
+
+  #[test]
+  fn test_iter_from_both_ends(initial_data in prop::collection::vec(any::<u8>(), 0..1000), picks in prop::collection::vec(any::<bool>(), 0..1000)) {
+    let tree = TernaryTreeList::from(initial_data.clone());
+    let mut it = tree.iter();
+    let mut front = Vec::new();
+    let mut back = Vec::new();
+
+    for from_back in picks {
+      let item = if from_back { it.next_back() } else { it.next() };
+      match item {
+        Some(x) if from_back => back.push(*x),
+        Some(x) => front.push(*x),
+        None => break,
+      }
+    }
+    front.extend(it.copied());
+    back.reverse();
+    front.extend(back);
+
+    prop_assert_eq!(front, initial_data, "both ends should meet in the middle");
+  }
+
+  #[test]
+  fn test_extend_matches_vec(initial_data in prop::collection::vec(any::<u8>(), 0..500), more in prop::collection::vec(any::<u8>(), 0..500)) {
+    let mut tree: TernaryTreeList<u8> = initial_data.iter().copied().collect();
+    tree.extend(more.iter().copied());
+    let mut vec = initial_data;
+    vec.extend(more);
+
+    prop_assert!(tree.check_structure().is_ok());
+    prop_assert_eq!(tree.len(), vec.len(), "Length should be consistent");
+    prop_assert_eq!(tree.to_vec(), vec, "TernaryTreeList should match Vec after extend");
+  }
+
+  #[test]
+  fn test_extend_many_times(batches in prop::collection::vec(prop::collection::vec(any::<u8>(), 0..4), 0..400)) {
+    let mut tree: TernaryTreeList<u8> = TernaryTreeList::Empty;
+    let mut vec = vec![];
+    for batch in batches {
+      tree.extend(batch.iter().copied());
+      tree = tree.append_iter(batch.iter().copied());
+      vec.extend(batch.iter().copied());
+      vec.extend(batch);
+    }
+
+    let report = tree.structure_report();
+    prop_assert!(report.max_depth <= report.depth_limit, "{:?}", report);
+    prop_assert_eq!(tree.to_vec(), vec);
+  }
+
+  #[test]
+  fn test_compare_matches_vec(xs in prop::collection::vec(0..4u8, 0..200), ys in prop::collection::vec(0..4u8, 0..200), idx in any::<usize>()) {
+    let a = TernaryTreeList::from(xs.clone());
+    let b = ys.iter().fold(TernaryTreeList::Empty, |acc, y| acc.push_right(*y));
+    prop_assert_eq!(a == b, xs == ys);
+    let expected = if cfg!(feature = "lexicographic-ord") {
+      xs.cmp(&ys)
+    } else {
+      xs.len().cmp(&ys.len()).then_with(|| xs.cmp(&ys))
+    };
+    prop_assert_eq!(a.cmp(&b), expected);
+    prop_assert_eq!(a.cmp_lexicographic(&b), xs.cmp(&ys));
+
+    if !xs.is_empty() {
+      // versions sharing most of the branches
+      let pos = idx % xs.len();
+      let changed = a.assoc(pos, 9).unwrap();
+      let mut zs = xs.clone();
+      zs[pos] = 9;
+      prop_assert_eq!(a == changed, xs == zs);
+      prop_assert_eq!(a.cmp(&changed), xs.cmp(&zs));
+    }
+  }
+
+  #[test]
+  fn test_hash_matches_slice(xs in prop::collection::vec(any::<u8>(), 0..300), at in any::<usize>(), extra in any::<u8>()) {
+    let split = if xs.is_empty() { 0 } else { at % (xs.len() + 1) };
+    let (head, tail) = xs.split_at(split);
+    let extended = {
+      let mut ys = vec![extra];
+      ys.extend_from_slice(&xs);
+      ys.push(extra);
+      TernaryTreeList::from(ys)
+    };
+
+    let lists: Vec<TernaryTreeList<u8>> = vec![
+      TernaryTreeList::from(xs.clone()),
+      TernaryTreeList::from(&xs),
+      xs.iter().copied().collect(),
+      xs.iter().fold(TernaryTreeList::Empty, |acc, x| acc.push_right(*x)),
+      xs.iter().rev().fold(TernaryTreeList::Empty, |acc, x| acc.push_left(*x)),
+      TernaryTreeList::concat(&[TernaryTreeList::from(head.to_vec()), TernaryTreeList::from(tail.to_vec())]),
+      TernaryTreeList::from(head.to_vec()).append_iter(tail.iter().copied()),
+      extended.range(1..=xs.len()).unwrap(),
+      extended.drop_left().drop_right(),
+    ];
+
+    let expected = hash_of(xs.as_slice());
+    prop_assert_eq!(hash_of(&xs), expected);
+    for list in &lists {
+      prop_assert_eq!(list.to_vec(), xs.clone());
+      prop_assert_eq!(hash_of(list), expected, "hash of {} should match slice", list.format_inline());
+      #[cfg(feature = "cached-hash")]
+      prop_assert_eq!(list.cached_hash(), lists[0].cached_hash());
+    }
+  }
+
+  #[test]
+  fn test_chunked_matches_vec(xs in prop::collection::vec(any::<u8>(), 0..300), ops in chunk_ops()) {
+    let (tree, vec) = edit_chunked(xs, ops)?;
+
+    prop_assert_eq!(tree.to_vec(), vec.clone());
+    prop_assert_eq!(tree.iter().rev().copied().collect::<Vec<_>>(), vec.iter().rev().copied().collect::<Vec<_>>());
+    prop_assert_eq!(tree.clone().into_iter().collect::<Vec<_>>(), vec.clone());
+    for (i, x) in vec.iter().enumerate() {
+      prop_assert_eq!(tree.get(i), Some(x));
+    }
+  }
+
+  #[test]
+  fn test_chunked_hash(xs in prop::collection::vec(any::<u8>(), 0..300), ops in chunk_ops()) {
+    let (tree, vec) = edit_chunked(xs, ops)?;
+
+    prop_assert_eq!(hash_of(&tree), hash_of(vec.as_slice()));
+    #[cfg(feature = "cached-hash")]
+    prop_assert_eq!(tree.cached_hash(), TernaryTreeList::from(vec.clone()).cached_hash());
+  }
+
+  #[test]
+  fn test_chunked_structure_report(xs in prop::collection::vec(any::<u8>(), 0..300), ops in chunk_ops()) {
+    let (tree, vec) = edit_chunked(xs, ops)?;
+
+    let report = tree.structure_report();
+    prop_assert!(report.is_valid(), "{:?}", report);
+    prop_assert_eq!(report.len, vec.len());
+    prop_assert!(!report.issues.iter().any(|x| matches!(x, StructureIssue::FingerTooDeep { .. })), "{:?}", report);
+  }
+
+  #[test]
+  fn test_chunked_sharing_stats(xs in prop::collection::vec(any::<u8>(), 0..300), ops in chunk_ops()) {
+    let (tree, _) = edit_chunked(xs, ops)?;
+
+    let versions = [tree.clone(), tree.push_left(0), tree.drop_right()];
+    let stats = TernaryTreeList::sharing_stats(&versions);
+    let nodes: usize = versions.iter().map(|x| {
+      let s = x.stats();
+      s.leaves + s.chunks + s.branch2 + s.branch3
+    }).sum();
+    prop_assert_eq!(stats.total_nodes, nodes);
+    prop_assert!(stats.unique_nodes <= stats.total_nodes);
+
+    // roots are held by value, so only the root of a clone counts as a new node
+    let once = TernaryTreeList::sharing_stats([&tree]);
+    let same = TernaryTreeList::sharing_stats(&[tree.clone(), tree.clone()]);
+    prop_assert_eq!(same.total_nodes, once.total_nodes * 2);
+    prop_assert_eq!(same.unique_nodes, once.unique_nodes + usize::from(!tree.is_empty()));
+  }
+
+  #[test]
+  fn test_chunked_parse_inline(xs in prop::collection::vec(any::<u8>(), 0..300), ops in chunk_ops()) {
+    let (tree, _) = edit_chunked(xs, ops)?;
+
+    let parsed = TernaryTreeList::<u8>::parse_inline(&tree.format_inline()).unwrap();
+    prop_assert!(parsed.eq_shape(&tree));
+  }
+
+  #[test]
+  fn test_chunked_snapshot(xs in prop::collection::vec(any::<u8>(), 0..300), ops in chunk_ops()) {
+    let (tree, _) = edit_chunked(xs, ops)?;
+
+    let versions = [tree.clone(), tree.push_left(0), tree.drop_right()];
+    let bytes = TernaryTreeList::write_snapshot(&versions, &ByteCodec);
+    let loaded = TernaryTreeList::read_snapshot(&bytes, &ByteCodec).unwrap();
+    for (x, y) in loaded.iter().zip(&versions) {
+      prop_assert!(x.eq_shape(y));
+    }
+    // heap bytes differ by capacities of chunks
+    let (stats, expected) = (TernaryTreeList::sharing_stats(&loaded), TernaryTreeList::sharing_stats(&versions));
+    prop_assert_eq!((stats.total_nodes, stats.unique_nodes), (expected.total_nodes, expected.unique_nodes));
+  }
+
+  #[test]
+  fn test_builder_matches_persistent(xs in prop::collection::vec(any::<u8>(), 0..300), chunked in any::<bool>(), ops in prop::collection::vec((0..5u8, any::<usize>(), any::<u8>()), 0..300)) {
+    let mut tree = if chunked { TernaryTreeList::from_chunked(xs) } else { TernaryTreeList::from(xs) };
+    let mut builder = tree.transient();
+    let snapshot = tree.clone();
+
+    for (op, idx, value) in ops {
+      match op {
+        // an empty builder starts with a chunk
+        0 if tree.is_empty() => {
+          builder.push_right(value);
+          tree = TernaryTreeList::from_chunked([value]);
+        }
+        1 if tree.is_empty() => {
+          builder.push_left(value);
+          tree = TernaryTreeList::from_chunked([value]);
+        }
+        0 => {
+          builder.push_right(value);
+          tree = tree.push_right(value);
+        }
+        1 => {
+          builder.push_left(value);
+          tree = tree.push_left(value);
+        }
+        2 => {
+          prop_assert_eq!(builder.pop_left(), tree.first().copied());
+          tree = tree.drop_left();
+        }
+        3 => {
+          prop_assert_eq!(builder.pop_right(), tree.last().copied());
+          tree = tree.drop_right();
+        }
+        _ => {
+          if !tree.is_empty() {
+            let pos = idx % tree.len();
+            builder.assoc(pos, value).unwrap();
+            tree = tree.assoc(pos, value).unwrap();
+          }
+        }
+      }
+      prop_assert_eq!(builder.len(), tree.len());
+    }
+
+    let built = builder.freeze();
+    prop_assert!(built.check_structure().is_ok());
+    prop_assert!(built.eq_shape(&tree), "{} should be in shape of {}", built.format_inline(), tree.format_inline());
+    #[cfg(feature = "cached-hash")]
+    prop_assert_eq!(built.cached_hash(), tree.cached_hash());
+    prop_assert!(snapshot.check_structure().is_ok());
+  }
+
+  #[test]
+  fn test_builder_from_empty(ops in prop::collection::vec((0..4u8, any::<u8>()), 0..500)) {
+    let mut builder = TernaryTreeListBuilder::new();
+    let mut vec = std::collections::VecDeque::new();
+    for (op, value) in ops {
+      match op {
+        0 => {
+          builder.push_right(value);
+          vec.push_back(value);
+        }
+        1 => {
+          builder.push_left(value);
+          vec.push_front(value);
+        }
+        2 => prop_assert_eq!(builder.pop_left(), vec.pop_front()),
+        _ => prop_assert_eq!(builder.pop_right(), vec.pop_back()),
+      }
+    }
+    prop_assert_eq!(builder.freeze().to_vec(), Vec::from(vec));
+  }
+
+  #[test]
+  fn test_edits_stay_in_depth_limit(xs in prop::collection::vec(any::<u8>(), 1..200), ops in prop::collection::vec((0..5u8, any::<usize>()), 0..600)) {
+    let mut tree = TernaryTreeList::from(xs);
+
+    for (op, idx) in ops {
+      if tree.is_empty() {
+        tree = tree.push_right(0);
+      }
+      let pos = idx % tree.len();
+      tree = match op {
+        0 => tree.insert(pos, 0, false).unwrap(),
+        1 => tree.insert(pos, 0, true).unwrap(),
+        2 => tree.insert(tree.len() / 2, 0, false).unwrap(),
+        3 => tree.dissoc(pos).unwrap(),
+        _ => tree.dissoc(tree.len() - 1).unwrap(),
+      };
+      let report = tree.structure_report();
+      prop_assert!(report.max_depth <= report.depth_limit, "{:?}", report);
+    }
+
+    let report = tree.structure_report();
+    prop_assert!(report.is_valid(), "{:?}", report);
+  }
 }
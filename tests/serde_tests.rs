@@ -0,0 +1,76 @@
+#![cfg(feature = "serde")]
+
+extern crate im_ternary_tree;
+
+use im_ternary_tree::TernaryTreeList;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[test]
+fn serialize_as_sequence() -> Result<(), serde_json::Error> {
+  let data = TernaryTreeList::from(&[1, 2, 3, 4, 5]);
+  assert_eq!(serde_json::to_string(&data)?, "[1,2,3,4,5]");
+
+  // shape does not change the output
+  let pushed = (1..=5).fold(TernaryTreeList::Empty, |acc, x| acc.push_left(6 - x));
+  assert!(!pushed.eq_shape(&data));
+  assert_eq!(serde_json::to_string(&pushed)?, "[1,2,3,4,5]");
+
+  let chunked = TernaryTreeList::from_chunked(0..40);
+  assert_eq!(
+    serde_json::to_string(&chunked)?,
+    serde_json::to_string(&(0..40).collect::<Vec<_>>())?
+  );
+
+  assert_eq!(serde_json::to_string(&TernaryTreeList::<usize>::Empty)?, "[]");
+  Ok(())
+}
+
+#[test]
+fn deserialize_balanced() -> Result<(), serde_json::Error> {
+  let data: TernaryTreeList<usize> = serde_json::from_str("[1,2,3,4,5]")?;
+  assert!(data.eq_shape(&TernaryTreeList::from_chunked([1, 2, 3, 4, 5])));
+
+  let xs: Vec<usize> = (0..1000).collect();
+  let data: TernaryTreeList<usize> = serde_json::from_str(&serde_json::to_string(&xs)?)?;
+  assert_eq!(data.to_vec(), xs);
+  assert!(data.structure_report().is_ok());
+
+  let empty: TernaryTreeList<usize> = serde_json::from_str("[]")?;
+  assert_eq!(empty, TernaryTreeList::Empty);
+
+  let nested: Vec<TernaryTreeList<String>> = serde_json::from_str(r#"[["a"], [], ["b", "c"]]"#)?;
+  assert_eq!(nested[2].to_vec(), vec!["b", "c"]);
+
+  assert!(serde_json::from_str::<TernaryTreeList<usize>>("{}").is_err());
+  assert!(serde_json::from_str::<TernaryTreeList<usize>>("[1, \"a\"]").is_err());
+  Ok(())
+}
+
+static CLONES: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, PartialEq)]
+struct CloneCounted(usize);
+
+impl Clone for CloneCounted {
+  fn clone(&self) -> Self {
+    CLONES.fetch_add(1, Ordering::SeqCst);
+    CloneCounted(self.0)
+  }
+}
+
+impl<'de> serde::Deserialize<'de> for CloneCounted {
+  fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    usize::deserialize(deserializer).map(CloneCounted)
+  }
+}
+
+#[test]
+fn deserialize_without_cloning() -> Result<(), serde_json::Error> {
+  let xs: Vec<usize> = (0..1000).collect();
+  let data: TernaryTreeList<CloneCounted> = serde_json::from_str(&serde_json::to_string(&xs)?)?;
+  assert_eq!(CLONES.load(Ordering::SeqCst), 0);
+  assert_eq!(data.len(), 1000);
+  assert_eq!(data[999], CloneCounted(999));
+  assert!(data.structure_report().is_ok());
+  Ok(())
+}